@@ -0,0 +1,208 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use macroquad::prelude::*;
+use macroquad::ui::{hash, widgets};
+use silica_engine::world::World;
+
+use crate::data::{create_autosave_dir, get_autosave_dir, get_data_dir, path_str, DataError};
+use crate::manager::GameProperties;
+use crate::utils::pinned_window;
+
+// snapshots kept in the autosave dir, each autosave replaces the oldest
+const AUTOSAVE_SLOTS: usize = 3;
+// seconds between autosaves, 0 turns them off
+pub const DEFAULT_AUTOSAVE_INTERVAL: f32 = 120.0;
+
+// base path of a snapshot slot, save_to_slc adds the .slc
+fn slot_path(slot: usize) -> Result<PathBuf, DataError> {
+    let mut path = get_autosave_dir()?;
+    path.push(format!("autosave-{}", slot));
+    Ok(path)
+}
+
+// mtime of the snapshot in each slot, None for slots not written yet
+fn slot_times() -> Vec<Option<SystemTime>> {
+    (0..AUTOSAVE_SLOTS)
+        .map(|slot| {
+            let path = slot_path(slot).ok()?.with_extension("slc");
+            std::fs::metadata(path).and_then(|m| m.modified()).ok()
+        })
+        .collect()
+}
+
+fn write_autosave(world: &World) -> Result<(), DataError> {
+    create_autosave_dir()?;
+    // empty slots first, then the oldest snapshot
+    let slot = slot_times()
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, time)| *time)
+        .map_or(0, |(slot, _)| slot);
+    let path = slot_path(slot)?;
+    world.save_to_slc(path_str(&path)?);
+    Ok(())
+}
+
+// writes snapshots on a background thread so a big world doesn't stall the frame it is saved on
+pub struct Autosaver {
+    // a snapshot is being written, the next one waits for it
+    busy: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    jobs: Sender<World>,
+    #[cfg(target_arch = "wasm32")]
+    done: Sender<Result<(), DataError>>,
+    results: Receiver<Result<(), DataError>>,
+}
+
+impl Autosaver {
+    pub fn new() -> Autosaver {
+        let (done, results) = channel();
+        // the worker stops once the autosaver and with it the sender is dropped
+        #[cfg(not(target_arch = "wasm32"))]
+        let jobs = {
+            let (jobs, snapshots) = channel::<World>();
+            std::thread::spawn(move || {
+                for world in snapshots {
+                    if done.send(write_autosave(&world)).is_err() {
+                        break;
+                    }
+                }
+            });
+            jobs
+        };
+
+        Autosaver {
+            busy: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            jobs,
+            #[cfg(target_arch = "wasm32")]
+            done,
+            results,
+        }
+    }
+
+    // the world is copied so it can keep running while the copy is written
+    pub fn save(&mut self, world: &World) {
+        if self.busy {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.busy = self.jobs.send(world.clone()).is_ok();
+        }
+        // no threads on the web, the snapshot is written right away
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.busy = self.done.send(write_autosave(world)).is_ok();
+        }
+    }
+
+    // result of the last snapshot once it is written, call once per frame
+    pub fn poll(&mut self) -> Option<Result<(), DataError>> {
+        let result = self.results.try_recv().ok()?;
+        self.busy = false;
+        Some(result)
+    }
+}
+
+// .slc of the newest snapshot
+pub fn latest_autosave() -> Option<PathBuf> {
+    let slot = slot_times()
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, time)| Some((slot, (*time)?)))
+        .max_by_key(|(_, time)| *time)?
+        .0;
+    Some(slot_path(slot).ok()?.with_extension("slc"))
+}
+
+// holds "running" while silica runs, it is only emptied when leaving through the exit dialog
+fn session_lock_path() -> Result<PathBuf, DataError> {
+    let mut path = get_data_dir()?;
+    path.push("session.lock");
+    Ok(path)
+}
+
+// the lock file stays open and locked by the os until this process is gone,
+// crashed or not, so a second silica can tell a running session from a dead one
+static SESSION: OnceLock<File> = OnceLock::new();
+
+// returns true when the last session didn't exit cleanly
+pub fn start_session() -> Result<bool, DataError> {
+    let path = session_lock_path()?;
+    let io_err = |err| DataError::Io(path.clone(), err);
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(io_err)?;
+    match file.try_lock() {
+        Ok(()) => (),
+        // a second silica started next to a running one leaves the session to the first
+        Err(TryLockError::WouldBlock) => return Ok(false),
+        Err(TryLockError::Error(err)) => return Err(io_err(err)),
+    }
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).map_err(io_err)?;
+    let crashed = !contents.trim().is_empty();
+    file.set_len(0).map_err(io_err)?;
+    file.rewind().map_err(io_err)?;
+    file.write_all(b"running\n").map_err(io_err)?;
+    let _ = SESSION.set(file);
+    Ok(crashed)
+}
+
+pub fn end_session() {
+    if let Some(file) = SESSION.get() {
+        let _ = file.set_len(0);
+    }
+}
+
+// shown at startup after a crash, returns true when restore was clicked
+pub fn draw_restore_dialog(props: &mut GameProperties) -> bool {
+    let dialog_width = screen_width() * 0.4;
+    let dialog_height = screen_height() * 0.25;
+    let button_height = 50.0;
+    let mut restore = false;
+
+    pinned_window(
+        hash!(),
+        vec2(
+            (screen_width() - dialog_width) / 2.0,
+            (screen_height() - dialog_height) / 2.0,
+        ),
+        vec2(dialog_width, dialog_height),
+        |ui| {
+            ui.label(None, "Silica didn't exit cleanly last time.");
+            ui.label(None, "Restore the most recent autosave?");
+
+            if widgets::Button::new("Restore")
+                .position(vec2(10.0, dialog_height - button_height - 20.0))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                restore = true;
+                props.requested_restore = false;
+            }
+
+            if widgets::Button::new("Discard")
+                .position(vec2(
+                    dialog_width / 2.0,
+                    dialog_height - button_height - 20.0,
+                ))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                props.requested_restore = false;
+            }
+        },
+    );
+    restore
+}
@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+use crate::data::get_brush_dir;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrushShape {
+    Circle,
+    Square,
+    Diamond,
+    HorizontalLine,
+    VerticalLine,
+    Custom,
+}
+
+impl Display for BrushShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrushShape::Circle => write!(f, "CIRC"),
+            BrushShape::Square => write!(f, "SQR"),
+            BrushShape::Diamond => write!(f, "DMND"),
+            BrushShape::HorizontalLine => write!(f, "HLIN"),
+            BrushShape::VerticalLine => write!(f, "VLIN"),
+            BrushShape::Custom => write!(f, "CSTM"),
+        }
+    }
+}
+
+// monochrome brush loaded from a png, dark opaque pixels are part of the brush
+pub struct BrushMask {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl BrushMask {
+    pub fn load(path: &PathBuf) -> Option<BrushMask> {
+        let bytes = std::fs::read(path).ok()?;
+        let image = Image::from_file_with_format(&bytes, None).ok()?;
+        let width = image.width();
+        let height = image.height();
+        let mut cells = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = image.get_pixel(x as u32, y as u32);
+                let brightness = (pixel.r + pixel.g + pixel.b) / 3.0;
+                cells.push(pixel.a > 0.5 && brightness < 0.5);
+            }
+        }
+        Some(BrushMask {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    // nearest neighbour lookup with u, v in 0..1
+    fn sample(&self, u: f32, v: f32) -> bool {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.cells[y * self.width + x]
+    }
+}
+
+pub fn list_custom_brushes() -> Vec<PathBuf> {
+    // no data dir just means no custom brushes
+    let Ok(brush_dir) = get_brush_dir() else {
+        return vec![];
+    };
+    let mut brushes: Vec<PathBuf> = std::fs::read_dir(brush_dir)
+        .map(|dir| {
+            dir.flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("png"))
+                .collect()
+        })
+        .unwrap_or_default();
+    brushes.sort();
+    brushes
+}
+
+// keeps the selected custom brush decoded so it isn't read from disk every frame
+#[derive(Default)]
+pub struct CustomBrushes {
+    loaded: Option<(usize, Option<BrushMask>)>,
+}
+
+impl CustomBrushes {
+    pub fn get(&mut self, idx: usize) -> Option<&BrushMask> {
+        if self.loaded.as_ref().map(|(loaded, _)| *loaded) != Some(idx) {
+            let brushes = list_custom_brushes();
+            let mask = if brushes.is_empty() {
+                None
+            } else {
+                BrushMask::load(&brushes[idx % brushes.len()])
+            };
+            self.loaded = Some((idx, mask));
+        }
+        self.loaded.as_ref().and_then(|(_, mask)| mask.as_ref())
+    }
+}
+
+// a unit-length edge between two cell corners
+type Edge = ((i32, i32), (i32, i32));
+
+// cells covered by the brush, relative to the cursor
+pub struct Brush {
+    pub offsets: Vec<(i32, i32)>,
+    // percentage of the covered cells that get painted, below 100 sprays
+    pub density: f32,
+    outline: Vec<Edge>,
+}
+
+impl Brush {
+    pub fn new(shape: BrushShape, radius: i32, density: f32, mask: Option<&BrushMask>) -> Brush {
+        let mut offsets = vec![];
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let covered = match shape {
+                    BrushShape::Circle => dx * dx + dy * dy < radius * radius,
+                    BrushShape::Square => dx.abs() < radius && dy.abs() < radius,
+                    BrushShape::Diamond => dx.abs() + dy.abs() < radius,
+                    BrushShape::HorizontalLine => dy == 0 && dx.abs() < radius,
+                    BrushShape::VerticalLine => dx == 0 && dy.abs() < radius,
+                    // the mask is stretched over the brush diameter
+                    BrushShape::Custom => match mask {
+                        Some(mask) => {
+                            let size = (radius * 2).max(1) as f32;
+                            mask.sample((dx + radius) as f32 / size, (dy + radius) as f32 / size)
+                        }
+                        None => dx * dx + dy * dy < radius * radius,
+                    },
+                };
+                if covered {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        // tiny brushes still paint the cell under the cursor
+        if offsets.is_empty() {
+            offsets.push((0, 0));
+        }
+        let outline = outline(&offsets);
+        Brush {
+            offsets,
+            density,
+            outline,
+        }
+    }
+
+    // unit-length edges around the footprint, in cell coords relative to the cursor cell
+    pub fn outline(&self) -> &[Edge] {
+        &self.outline
+    }
+}
+
+fn outline(offsets: &[(i32, i32)]) -> Vec<Edge> {
+    let cells: HashSet<(i32, i32)> = offsets.iter().copied().collect();
+    let mut edges = vec![];
+    for &(x, y) in offsets.iter() {
+        if !cells.contains(&(x - 1, y)) {
+            edges.push(((x, y), (x, y + 1)));
+        }
+        if !cells.contains(&(x + 1, y)) {
+            edges.push(((x + 1, y), (x + 1, y + 1)));
+        }
+        if !cells.contains(&(x, y - 1)) {
+            edges.push(((x, y), (x + 1, y)));
+        }
+        if !cells.contains(&(x, y + 1)) {
+            edges.push(((x, y + 1), (x + 1, y + 1)));
+        }
+    }
+    edges
+}
+
+// the brush and its outline are only rebuilt when the shape, radius or custom png changes
+#[derive(Default)]
+pub struct BrushCache {
+    custom_brushes: CustomBrushes,
+    built: Option<((BrushShape, i32, usize), Brush)>,
+}
+
+impl BrushCache {
+    pub fn get(
+        &mut self,
+        shape: BrushShape,
+        radius: i32,
+        density: f32,
+        custom_idx: usize,
+    ) -> &Brush {
+        // the custom index only matters for custom brushes
+        let custom_idx = if shape == BrushShape::Custom {
+            custom_idx
+        } else {
+            0
+        };
+        let key = (shape, radius, custom_idx);
+        if self.built.as_ref().map(|(built, _)| *built) != Some(key) {
+            self.built = None;
+        }
+        let custom_brushes = &mut self.custom_brushes;
+        let (_, brush) = self.built.get_or_insert_with(|| {
+            let mask = match shape {
+                BrushShape::Custom => custom_brushes.get(custom_idx),
+                _ => None,
+            };
+            (key, Brush::new(shape, radius, density, mask))
+        });
+        brush.density = density;
+        brush
+    }
+}
+
+// whether the spray paints each cell, rolled once per stroke so overlapping stamps
+// don't push the fill above the density
+#[derive(Default)]
+pub struct Spray {
+    decisions: HashMap<(i32, i32), bool>,
+}
+
+impl Spray {
+    // roll is only called the first time a cell is sprayed in the open stroke
+    pub fn decide(&mut self, x: i32, y: i32, roll: impl FnOnce() -> bool) -> bool {
+        *self.decisions.entry((x, y)).or_insert_with(roll)
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.decisions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_brush_covers_its_box() {
+        let brush = Brush::new(BrushShape::Square, 2, 100.0, None);
+        assert_eq!(brush.offsets.len(), 9);
+        assert!(brush
+            .offsets
+            .iter()
+            .all(|&(dx, dy)| dx.abs() <= 1 && dy.abs() <= 1));
+        // a 3x3 block has three edges on each side
+        assert_eq!(brush.outline().len(), 12);
+    }
+
+    #[test]
+    fn tiny_brushes_paint_the_cursor_cell() {
+        for shape in [BrushShape::Circle, BrushShape::Diamond, BrushShape::Square] {
+            let brush = Brush::new(shape, 0, 100.0, None);
+            assert_eq!(brush.offsets, vec![(0, 0)]);
+            assert_eq!(brush.outline().len(), 4);
+        }
+    }
+
+    #[test]
+    fn line_brushes_are_one_cell_thick() {
+        let horizontal = Brush::new(BrushShape::HorizontalLine, 3, 100.0, None);
+        assert_eq!(horizontal.offsets.len(), 5);
+        assert!(horizontal.offsets.iter().all(|&(_, dy)| dy == 0));
+        let vertical = Brush::new(BrushShape::VerticalLine, 3, 100.0, None);
+        assert!(vertical.offsets.iter().all(|&(dx, _)| dx == 0));
+    }
+
+    #[test]
+    fn custom_brush_without_a_mask_is_a_circle() {
+        let custom = Brush::new(BrushShape::Custom, 4, 100.0, None);
+        let circle = Brush::new(BrushShape::Circle, 4, 100.0, None);
+        assert_eq!(custom.offsets, circle.offsets);
+    }
+
+    #[test]
+    fn spray_is_rolled_once_per_cell_per_stroke() {
+        let mut spray = Spray::default();
+        assert!(spray.decide(0, 0, || true));
+        assert!(spray.decide(0, 0, || false));
+        spray.end_stroke();
+        assert!(!spray.decide(0, 0, || false));
+    }
+}
@@ -0,0 +1,110 @@
+use macroquad::prelude::*;
+
+pub const MAX_ZOOM: f32 = 32.0;
+
+// part of the world shown in the view, zoom 1 shows all of it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub zoom: f32,
+    // top left of the visible part in world coords
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            zoom: 1.0,
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    // source rect for the world texture
+    pub fn visible_rect(&self, world_width: usize, world_height: usize) -> Rect {
+        Rect::new(
+            self.x,
+            self.y,
+            world_width as f32 / self.zoom,
+            world_height as f32 / self.zoom,
+        )
+    }
+
+    // keeps the visible part inside the world
+    fn clamp(&mut self, world_width: usize, world_height: usize) {
+        let rect = self.visible_rect(world_width, world_height);
+        self.x = self.x.clamp(0.0, world_width as f32 - rect.w);
+        self.y = self.y.clamp(0.0, world_height as f32 - rect.h);
+    }
+
+    // zooms by factor keeping the world point (x, y) where it is on screen
+    pub fn zoom_at(
+        &mut self,
+        factor: f32,
+        x: f32,
+        y: f32,
+        world_width: usize,
+        world_height: usize,
+    ) {
+        let before = self.visible_rect(world_width, world_height);
+        let frac_x = (x - before.x) / before.w;
+        let frac_y = (y - before.y) / before.h;
+        self.zoom = (self.zoom * factor).clamp(1.0, MAX_ZOOM);
+        let after = self.visible_rect(world_width, world_height);
+        self.x = x - frac_x * after.w;
+        self.y = y - frac_y * after.h;
+        self.clamp(world_width, world_height);
+    }
+
+    // moves the view so the world point (x, y) ends up at frac_x, frac_y of the view
+    pub fn grab(
+        &mut self,
+        x: f32,
+        y: f32,
+        frac_x: f32,
+        frac_y: f32,
+        world_width: usize,
+        world_height: usize,
+    ) {
+        let rect = self.visible_rect(world_width, world_height);
+        self.x = x - frac_x * rect.w;
+        self.y = y - frac_y * rect.h;
+        self.clamp(world_width, world_height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let mut camera = Camera::default();
+        camera.zoom_at(4.0, 40.0, 30.0, 200, 100);
+        assert_eq!(camera.zoom, 4.0);
+        // 40 was a fifth of the way across, so it still is
+        let rect = camera.visible_rect(200, 100);
+        assert_eq!((40.0 - rect.x) / rect.w, 0.2);
+        assert_eq!((30.0 - rect.y) / rect.h, 0.3);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::default();
+        camera.zoom_at(0.5, 10.0, 10.0, 100, 100);
+        assert_eq!(camera, Camera::default());
+        camera.zoom_at(1000.0, 10.0, 10.0, 100, 100);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+    }
+
+    #[test]
+    fn view_stays_inside_the_world() {
+        let mut camera = Camera::default();
+        camera.zoom_at(2.0, 100.0, 100.0, 100, 100);
+        assert_eq!((camera.x, camera.y), (50.0, 50.0));
+        camera.grab(0.0, 0.0, 0.5, 0.5, 100, 100);
+        assert_eq!((camera.x, camera.y), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,435 @@
+use macroquad::prelude::*;
+use macroquad::ui::{hash, widgets};
+use silica_engine::prelude::particle_to_color;
+use silica_engine::{variant::Variant, world::World};
+
+use crate::history::History;
+use crate::manager::{GameProperties, WorldInfo};
+use crate::utils::{pinned_window, screen_to_world, world_to_screen};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RegionCell {
+    pub variant: Variant,
+    // ambient temperature of the cell
+    pub temperature: f32,
+    // temperature of the particle itself
+    pub particle_temperature: f32,
+    // only used to draw previews, the variant is what gets pasted
+    pub color: (u8, u8, u8, u8),
+}
+
+// a rectangular snapshot of the world, stored row by row
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<RegionCell>,
+}
+
+impl Region {
+    // copies the cells between two corners, both inclusive and clamped to the world
+    pub fn capture(world: &World, start: (i32, i32), end: (i32, i32)) -> Option<Region> {
+        let min_x = start.0.min(end.0).max(0);
+        let min_y = start.1.min(end.1).max(0);
+        let max_x = start.0.max(end.0).min(world.width as i32 - 1);
+        let max_y = start.1.max(end.1).min(world.height as i32 - 1);
+        if max_x < min_x || max_y < min_y {
+            return None;
+        }
+
+        let width = (max_x - min_x + 1) as usize;
+        let height = (max_y - min_y + 1) as usize;
+        let mut cells = Vec::with_capacity(width * height);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let particle = world.get_particle(x, y);
+                cells.push(RegionCell {
+                    variant: particle.variant,
+                    temperature: world.get_temperature(x, y),
+                    particle_temperature: particle.temperature,
+                    color: particle_to_color(particle).to_rgba8(),
+                });
+            }
+        }
+
+        Some(Region {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> RegionCell {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn rotate_clockwise(&self) -> Region {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.width {
+            for x in 0..self.height {
+                cells.push(self.get(y, self.height - 1 - x));
+            }
+        }
+        Region {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    pub fn flip_horizontal(&self) -> Region {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                cells.push(self.get(self.width - 1 - x, y));
+            }
+        }
+        Region { cells, ..*self }
+    }
+
+    pub fn flip_vertical(&self) -> Region {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                cells.push(self.get(x, self.height - 1 - y));
+            }
+        }
+        Region { cells, ..*self }
+    }
+
+    // top left corner that puts the middle of the region on (x, y)
+    pub fn origin_centered_on(&self, x: i32, y: i32) -> (i32, i32) {
+        (x - self.width as i32 / 2, y - self.height as i32 / 2)
+    }
+
+    // empty cells are left out so pasted machines don't punch holes in what is already there
+    pub fn paste(&self, world: &mut World, history: &mut History, origin: (i32, i32)) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.get(x, y);
+                if cell.variant == Variant::Empty {
+                    continue;
+                }
+                let wx = origin.0 + x as i32;
+                let wy = origin.1 + y as i32;
+                if wx < 0 || wy < 0 || wx >= world.width as i32 || wy >= world.height as i32 {
+                    continue;
+                }
+                history.set_particle(world, wx, wy, cell.variant);
+                // cells the engine refused to overwrite, like walls, keep their own temperature
+                if world.get_particle(wx, wy).variant == cell.variant {
+                    history.set_particle_temperature(world, wx, wy, cell.particle_temperature);
+                }
+                let heat = cell.temperature - world.get_temperature(wx, wy);
+                history.add_heat(world, wx, wy, heat);
+            }
+        }
+    }
+
+    // standalone world holding just this region, used to store stamps with the engine's format
+    pub fn to_world(&self) -> World {
+        let mut world = World::new(self.width as i32, self.height as i32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.get(x, y);
+                let (wx, wy) = (x as i32, y as i32);
+                world.set_particle(wx, wy, cell.variant);
+                // set_temperature is the particle's own, ambient heat can only be added
+                if cell.variant != Variant::Empty {
+                    world.set_temperature(wx, wy, cell.particle_temperature);
+                }
+                let heat = cell.temperature - world.get_temperature(wx, wy);
+                world.add_heat(wx, wy, heat);
+            }
+        }
+        world
+    }
+
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::gen_image_color(self.width as u16, self.height as u16, BLANK);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.get(x, y);
+                let (r, g, b, _) = cell.color;
+                let alpha = if cell.variant == Variant::Empty {
+                    0
+                } else {
+                    255
+                };
+                image.set_pixel(x as u32, y as u32, Color::from_rgba(r, g, b, alpha));
+            }
+        }
+        image
+    }
+}
+
+// removes everything between the two corners, walls included
+pub fn clear_region(world: &mut World, history: &mut History, start: (i32, i32), end: (i32, i32)) {
+    for x in start.0.min(end.0)..=start.0.max(end.0) {
+        for y in start.1.min(end.1)..=start.1.max(end.1) {
+            if x < 0 || y < 0 || x >= world.width as i32 || y >= world.height as i32 {
+                continue;
+            }
+            if world.get_particle(x, y).variant == Variant::Wall {
+                history.erase_indestructible(world, x, y);
+            }
+            history.set_particle(world, x, y, Variant::Empty);
+        }
+    }
+}
+
+// holds the last copied region along with a texture for the paste ghost
+#[derive(Default)]
+pub struct Clipboard {
+    pub region: Option<Region>,
+    texture: Option<Texture2D>,
+}
+
+impl Clipboard {
+    pub fn set(&mut self, region: Region) {
+        let texture = Texture2D::from_image(&region.to_image());
+        texture.set_filter(FilterMode::Nearest);
+        self.texture = Some(texture);
+        self.region = Some(region);
+    }
+
+    pub fn transform(&mut self, f: impl Fn(&Region) -> Region) {
+        if let Some(region) = &self.region {
+            let transformed = f(region);
+            self.set(transformed);
+        }
+    }
+}
+
+pub fn draw_selection(world_info: &WorldInfo) {
+    let Some((start, end)) = world_info.properties.selection else {
+        return;
+    };
+    let (x0, y0) = world_to_screen(
+        start.0.min(end.0) as f32,
+        start.1.min(end.1) as f32,
+        world_info,
+    );
+    let (x1, y1) = world_to_screen(
+        (start.0.max(end.0) + 1) as f32,
+        (start.1.max(end.1) + 1) as f32,
+        world_info,
+    );
+    draw_rectangle(x0, y0, x1 - x0, y1 - y0, Color::new(0.3, 0.6, 1.0, 0.15));
+    draw_rectangle_lines(
+        x0,
+        y0,
+        x1 - x0,
+        y1 - y0,
+        2.0,
+        Color::new(0.3, 0.6, 1.0, 1.0),
+    );
+}
+
+// translucent copy of the clipboard that follows the cursor while pasting
+pub fn draw_paste_preview(clipboard: &Clipboard, world_info: &WorldInfo) {
+    let (Some(region), Some(texture)) = (&clipboard.region, &clipboard.texture) else {
+        return;
+    };
+    let (mouse_x, mouse_y) = mouse_position();
+    let (cursor_x, cursor_y) = screen_to_world(mouse_x, mouse_y, world_info);
+    let origin = region.origin_centered_on(cursor_x as i32, cursor_y as i32);
+    let (x0, y0) = world_to_screen(origin.0 as f32, origin.1 as f32, world_info);
+    let (x1, y1) = world_to_screen(
+        (origin.0 + region.width as i32) as f32,
+        (origin.1 + region.height as i32) as f32,
+        world_info,
+    );
+    draw_texture_ex(
+        texture,
+        x0,
+        y0,
+        Color::new(1.0, 1.0, 1.0, 0.6),
+        DrawTextureParams {
+            dest_size: Some(vec2(x1 - x0, y1 - y0)),
+            ..Default::default()
+        },
+    );
+    draw_rectangle_lines(
+        x0,
+        y0,
+        x1 - x0,
+        y1 - y0,
+        1.0,
+        Color::new(1.0, 1.0, 1.0, 0.6),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipboardAction {
+    Copy,
+    Cut,
+    Paste,
+    Rotate,
+    FlipHorizontal,
+    FlipVertical,
+    Done,
+    SaveStamp,
+    OpenStamps,
+}
+
+// buttons for the clipboard, only the ones that make sense right now are shown
+pub fn draw_clipboard_panel(
+    clipboard: &Clipboard,
+    world_info: &WorldInfo,
+) -> Option<ClipboardAction> {
+    let mut actions = vec![];
+    if world_info.properties.pasting {
+        actions.push((ClipboardAction::Rotate, "ROT"));
+        actions.push((ClipboardAction::FlipHorizontal, "FLPH"));
+        actions.push((ClipboardAction::FlipVertical, "FLPV"));
+        actions.push((ClipboardAction::Done, "DONE"));
+    } else {
+        if world_info.properties.selection.is_some() {
+            actions.push((ClipboardAction::Copy, "COPY"));
+            actions.push((ClipboardAction::Cut, "CUT"));
+            actions.push((ClipboardAction::SaveStamp, "STMP"));
+        }
+        if clipboard.region.is_some() {
+            actions.push((ClipboardAction::Paste, "PSTE"));
+        }
+        actions.push((ClipboardAction::OpenStamps, "LIB"));
+    }
+
+    let button_width = 50.0;
+    let panel_height = 30.0;
+    // sized for the most buttons that can show up at once
+    let panel_width = button_width * 5.0;
+    let mut clicked = None;
+    pinned_window(
+        hash!(),
+        vec2((screen_width() - panel_width) / 2.0, 0.0),
+        vec2(panel_width, panel_height),
+        |ui| {
+            let mut x = 0.0;
+            for (action, label) in actions.iter() {
+                if widgets::Button::new(*label)
+                    .position(vec2(x, 0.0))
+                    .size(vec2(button_width, panel_height))
+                    .ui(ui)
+                {
+                    clicked = Some(*action);
+                }
+                x += button_width;
+            }
+        },
+    );
+    clicked
+}
+
+pub fn handle_clipboard_action(
+    action: ClipboardAction,
+    clipboard: &mut Clipboard,
+    world: &mut World,
+    history: &mut History,
+    props: &mut GameProperties,
+) {
+    match action {
+        ClipboardAction::Copy | ClipboardAction::Cut => {
+            let Some((start, end)) = props.selection else {
+                return;
+            };
+            if let Some(region) = Region::capture(world, start, end) {
+                clipboard.set(region);
+            }
+            if action == ClipboardAction::Cut {
+                clear_region(world, history, start, end);
+                history.commit();
+            }
+        }
+        ClipboardAction::Paste => {
+            props.pasting = clipboard.region.is_some();
+        }
+        ClipboardAction::Rotate => clipboard.transform(Region::rotate_clockwise),
+        ClipboardAction::FlipHorizontal => clipboard.transform(Region::flip_horizontal),
+        ClipboardAction::FlipVertical => clipboard.transform(Region::flip_vertical),
+        ClipboardAction::Done => props.pasting = false,
+        // the stamp dialogs live in the main loop
+        ClipboardAction::SaveStamp | ClipboardAction::OpenStamps => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // cells numbered row by row through their particle temperature
+    fn numbered(width: usize, height: usize) -> Region {
+        let cells = (0..width * height)
+            .map(|idx| RegionCell {
+                variant: Variant::Sand,
+                temperature: 0.0,
+                particle_temperature: idx as f32,
+                color: (0, 0, 0, 255),
+            })
+            .collect();
+        Region {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    fn numbers(region: &Region) -> Vec<usize> {
+        region
+            .cells
+            .iter()
+            .map(|cell| cell.particle_temperature as usize)
+            .collect()
+    }
+
+    #[test]
+    fn rotate_clockwise_swaps_the_size() {
+        let rotated = numbered(3, 2).rotate_clockwise();
+        assert_eq!((rotated.width, rotated.height), (2, 3));
+        assert_eq!(numbers(&rotated), [3, 0, 4, 1, 5, 2]);
+    }
+
+    #[test]
+    fn four_rotations_are_the_original() {
+        let region = numbered(3, 2);
+        let rotated = region
+            .rotate_clockwise()
+            .rotate_clockwise()
+            .rotate_clockwise()
+            .rotate_clockwise();
+        assert_eq!((rotated.width, rotated.height), (3, 2));
+        assert_eq!(numbers(&rotated), numbers(&region));
+    }
+
+    #[test]
+    fn flips_mirror_rows_and_columns() {
+        let region = numbered(3, 2);
+        assert_eq!(numbers(&region.flip_horizontal()), [2, 1, 0, 5, 4, 3]);
+        assert_eq!(numbers(&region.flip_vertical()), [3, 4, 5, 0, 1, 2]);
+    }
+
+    #[test]
+    fn capture_is_clamped_to_the_world() {
+        let world = World::new(4, 4);
+        let region = Region::capture(&world, (2, 3), (-5, 1)).unwrap();
+        assert_eq!((region.width, region.height), (3, 3));
+        assert!(Region::capture(&world, (5, 5), (9, 9)).is_none());
+    }
+
+    #[test]
+    fn paste_keeps_particle_temperatures() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(0);
+        history.set_particle(&mut world, 1, 1, Variant::Sand);
+        history.set_particle_temperature(&mut world, 1, 1, 300.0);
+        let region = Region::capture(&world, (0, 0), (2, 2)).unwrap();
+
+        region.paste(&mut world, &mut history, (5, 5));
+        let pasted = world.get_particle(6, 6);
+        assert_eq!(pasted.variant, Variant::Sand);
+        assert_eq!(pasted.temperature, 300.0);
+        // empty cells of the region leave the world alone
+        assert_eq!(world.get_particle(5, 5).variant, Variant::Empty);
+    }
+}
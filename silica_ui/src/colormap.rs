@@ -0,0 +1,223 @@
+use std::fmt::Display;
+
+use macroquad::prelude::*;
+use macroquad::ui::hash;
+
+use crate::manager::WorldInfo;
+use crate::utils::{pinned_window, world_view_size};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMap {
+    Classic,
+    Inferno,
+    Viridis,
+    Grayscale,
+}
+
+impl Display for ColorMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorMap::Classic => write!(f, "classic"),
+            ColorMap::Inferno => write!(f, "inferno"),
+            ColorMap::Viridis => write!(f, "viridis"),
+            ColorMap::Grayscale => write!(f, "grayscale"),
+        }
+    }
+}
+
+pub const COLOR_MAPS: [ColorMap; 4] = [
+    ColorMap::Classic,
+    ColorMap::Inferno,
+    ColorMap::Viridis,
+    ColorMap::Grayscale,
+];
+
+// evenly spaced stops, classic is the original blue to red ramp
+// inferno and viridis are sampled from matplotlib's tables
+const CLASSIC: [(u8, u8, u8); 4] = [(0, 0, 255), (0, 255, 0), (255, 255, 0), (255, 0, 0)];
+const INFERNO: [(u8, u8, u8); 9] = [
+    (0, 0, 4),
+    (31, 12, 72),
+    (85, 15, 109),
+    (136, 34, 106),
+    (186, 54, 85),
+    (227, 89, 51),
+    (249, 140, 10),
+    (249, 201, 50),
+    (252, 255, 164),
+];
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (68, 1, 84),
+    (71, 44, 122),
+    (59, 81, 139),
+    (44, 113, 142),
+    (33, 144, 141),
+    (39, 173, 129),
+    (92, 200, 99),
+    (170, 220, 50),
+    (253, 231, 37),
+];
+const GRAYSCALE: [(u8, u8, u8); 2] = [(0, 0, 0), (255, 255, 255)];
+
+impl ColorMap {
+    fn stops(&self) -> &'static [(u8, u8, u8)] {
+        match self {
+            ColorMap::Classic => &CLASSIC,
+            ColorMap::Inferno => &INFERNO,
+            ColorMap::Viridis => &VIRIDIS,
+            ColorMap::Grayscale => &GRAYSCALE,
+        }
+    }
+
+    // t is clamped to 0..1, colors between stops are interpolated linearly
+    pub fn sample(&self, t: f32) -> (u8, u8, u8) {
+        let stops = self.stops();
+        let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let idx = (scaled as usize).min(stops.len() - 2);
+        let frac = scaled - idx as f32;
+        let (a, b) = (stops[idx], stops[idx + 1]);
+        let lerp = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * frac) as u8;
+        (lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+    }
+}
+
+// color map and temperature range used by the heat view
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeatScale {
+    pub map: ColorMap,
+    pub min: f32,
+    pub max: f32,
+    // follow the coldest and hottest cell on screen instead of min and max
+    pub auto_range: bool,
+}
+
+impl HeatScale {
+    pub const DEFAULT: HeatScale = HeatScale {
+        map: ColorMap::Classic,
+        min: 0.0,
+        max: 300.0,
+        auto_range: false,
+    };
+
+    pub fn color(&self, temperature: f32) -> (u8, u8, u8) {
+        let span = (self.max - self.min).max(f32::EPSILON);
+        self.map.sample((temperature - self.min) / span)
+    }
+
+    // widens a flat range so a uniform world doesn't divide by zero
+    pub fn fit(&mut self, min: f32, max: f32) {
+        if max - min < 1.0 {
+            let mid = (min + max) / 2.0;
+            self.min = mid - 0.5;
+            self.max = mid + 0.5;
+        } else {
+            self.min = min;
+            self.max = max;
+        }
+    }
+}
+
+// map, range and a gradient bar with the temperature scale, shown in heat mode
+pub fn draw_heat_legend(world_info: &mut WorldInfo) {
+    let scale = &mut world_info.properties.heat_scale;
+    let (view_w, view_h) = world_view_size();
+    let window_width = 260.0;
+    let window_height = 150.0;
+
+    pinned_window(
+        hash!(),
+        vec2(view_w - window_width, view_h - window_height),
+        vec2(window_width, window_height),
+        |ui| {
+            let names: Vec<String> = COLOR_MAPS.iter().map(|map| map.to_string()).collect();
+            let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+            let mut map_idx = COLOR_MAPS
+                .iter()
+                .position(|map| *map == scale.map)
+                .unwrap_or(0);
+            ui.combo_box(hash!(), "Color map", &names, &mut map_idx);
+            scale.map = COLOR_MAPS[map_idx];
+
+            ui.checkbox(hash!(), "Auto range", &mut scale.auto_range);
+            if !scale.auto_range {
+                ui.slider(hash!(), "Min C", -300.0..1000.0, &mut scale.min);
+                ui.slider(hash!(), "Max C", -300.0..5000.0, &mut scale.max);
+                if scale.max <= scale.min {
+                    scale.max = scale.min + 1.0;
+                }
+            }
+        },
+    );
+
+    // the bar is drawn straight to the screen, just above the window
+    let bar_x = view_w - window_width;
+    let bar_y = view_h - window_height - 40.0;
+    let bar_height = 12.0;
+    let steps = 64;
+    let step_width = window_width / steps as f32;
+    for i in 0..steps {
+        let (r, g, b) = scale.map.sample(i as f32 / (steps - 1) as f32);
+        draw_rectangle(
+            bar_x + i as f32 * step_width,
+            bar_y,
+            step_width.ceil(),
+            bar_height,
+            Color::from_rgba(r, g, b, 255),
+        );
+    }
+    draw_rectangle_lines(bar_x, bar_y, window_width, bar_height, 1.0, WHITE);
+
+    let label_y = bar_y + bar_height + 14.0;
+    let mid = (scale.min + scale.max) / 2.0;
+    draw_text(&format!("{:.0}C", scale.min), bar_x, label_y, 18.0, WHITE);
+    let mid_label = format!("{:.0}C", mid);
+    let mid_width = measure_text(&mid_label, None, 18, 1.0).width;
+    draw_text(
+        &mid_label,
+        bar_x + (window_width - mid_width) / 2.0,
+        label_y,
+        18.0,
+        WHITE,
+    );
+    let max_label = format!("{:.0}C", scale.max);
+    let max_width = measure_text(&max_label, None, 18, 1.0).width;
+    draw_text(
+        &max_label,
+        bar_x + window_width - max_width,
+        label_y,
+        18.0,
+        WHITE,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_start_and_end_on_their_stops() {
+        for map in COLOR_MAPS {
+            let stops = map.stops();
+            assert_eq!(map.sample(0.0), stops[0]);
+            assert_eq!(map.sample(1.0), stops[stops.len() - 1]);
+        }
+    }
+
+    #[test]
+    fn sample_clamps_and_interpolates() {
+        assert_eq!(ColorMap::Grayscale.sample(-1.0), (0, 0, 0));
+        assert_eq!(ColorMap::Grayscale.sample(2.0), (255, 255, 255));
+        assert_eq!(ColorMap::Grayscale.sample(0.5), (127, 127, 127));
+        assert_eq!(ColorMap::Classic.sample(0.5), (127, 255, 0));
+    }
+
+    #[test]
+    fn fit_widens_a_flat_range() {
+        let mut scale = HeatScale::DEFAULT;
+        scale.fit(20.0, 20.0);
+        assert_eq!((scale.min, scale.max), (19.5, 20.5));
+        scale.fit(-10.0, 90.0);
+        assert_eq!((scale.min, scale.max), (-10.0, 90.0));
+        assert_eq!(scale.color(40.0), scale.map.sample(0.5));
+    }
+}
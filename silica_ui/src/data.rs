@@ -1,45 +1,136 @@
+use std::fmt::Display;
 use std::path::{Path, PathBuf};
 
-pub fn get_data_dir() -> PathBuf {
+use macroquad::prelude::Image;
+
+// everything that can go wrong reading or writing the data dir
+#[derive(Debug)]
+pub enum DataError {
+    // none of APPDATA, XDG_DATA_HOME or HOME is set
+    NoDataDir,
+    Io(PathBuf, std::io::Error),
+    // the engine takes paths as &str
+    NonUtf8Path(PathBuf),
+    // a png that couldn't be encoded or written
+    Image(PathBuf, String),
+}
+
+impl Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataError::NoDataDir => write!(f, "no data dir, set HOME or APPDATA"),
+            DataError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            DataError::NonUtf8Path(path) => {
+                write!(f, "{}: path is not valid utf-8", path.display())
+            }
+            DataError::Image(path, err) => write!(f, "{}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+pub fn get_data_dir() -> Result<PathBuf, DataError> {
     // if windows, use %APPDATA% roaming then silica
     // if linux, use $XDG_DATA_HOME/silica
 
     let mut data_dir = PathBuf::new();
     if cfg!(windows) {
-        data_dir.push(std::env::var("APPDATA").unwrap());
+        data_dir.push(std::env::var_os("APPDATA").ok_or(DataError::NoDataDir)?);
+    } else if let Some(xdg_data_home) = std::env::var_os("XDG_DATA_HOME") {
+        data_dir.push(xdg_data_home);
     } else {
-        data_dir.push(std::env::var("XDG_DATA_HOME").unwrap_or_else(|_| {
-            let mut home = PathBuf::new();
-            home.push(std::env::var("HOME").unwrap());
-            home.push(".local/share");
-            home.to_str().unwrap().to_string()
-        }));
+        data_dir.push(std::env::var_os("HOME").ok_or(DataError::NoDataDir)?);
+        data_dir.push(".local/share");
     }
 
     data_dir.push("silica");
 
-    data_dir
+    Ok(data_dir)
 }
 
-pub fn create_data_dir() {
-    let data_dir = get_data_dir();
-    if !data_dir.exists() {
-        std::fs::create_dir_all(data_dir).unwrap();
-    }
+pub fn create_data_dir() -> Result<(), DataError> {
+    create_dir(&get_data_dir()?)?;
 
     // create saves dir
-    create_save_dir();
+    create_save_dir()?;
+    create_stamp_dir()?;
+    create_brush_dir()?;
+    create_autosave_dir()
 }
 
-pub fn create_save_dir() {
-    let save_dir = get_save_dir();
-    if !save_dir.exists() {
-        std::fs::create_dir_all(save_dir).unwrap();
+fn create_dir(dir: &Path) -> Result<(), DataError> {
+    if !dir.exists() {
+        std::fs::create_dir_all(dir).map_err(|err| DataError::Io(dir.to_path_buf(), err))?;
     }
+    Ok(())
 }
 
-pub fn get_save_dir() -> PathBuf {
-    let mut save_dir = get_data_dir();
+pub fn create_save_dir() -> Result<(), DataError> {
+    create_dir(&get_save_dir()?)
+}
+
+pub fn get_save_dir() -> Result<PathBuf, DataError> {
+    let mut save_dir = get_data_dir()?;
     save_dir.push("saves");
-    save_dir
+    Ok(save_dir)
+}
+
+pub fn create_stamp_dir() -> Result<(), DataError> {
+    create_dir(&get_stamp_dir()?)
+}
+
+pub fn get_stamp_dir() -> Result<PathBuf, DataError> {
+    let mut stamp_dir = get_data_dir()?;
+    stamp_dir.push("stamps");
+    Ok(stamp_dir)
+}
+
+pub fn create_brush_dir() -> Result<(), DataError> {
+    create_dir(&get_brush_dir()?)
+}
+
+pub fn get_brush_dir() -> Result<PathBuf, DataError> {
+    let mut brush_dir = get_data_dir()?;
+    brush_dir.push("brushes");
+    Ok(brush_dir)
+}
+
+pub fn create_autosave_dir() -> Result<(), DataError> {
+    create_dir(&get_autosave_dir()?)
+}
+
+pub fn get_autosave_dir() -> Result<PathBuf, DataError> {
+    let mut autosave_dir = get_data_dir()?;
+    autosave_dir.push("autosave");
+    Ok(autosave_dir)
+}
+
+pub fn get_settings_path() -> Result<PathBuf, DataError> {
+    let mut settings_path = get_data_dir()?;
+    settings_path.push("settings.cfg");
+    Ok(settings_path)
+}
+
+pub fn path_str(path: &Path) -> Result<&str, DataError> {
+    path.to_str()
+        .ok_or_else(|| DataError::NonUtf8Path(path.to_path_buf()))
+}
+
+pub fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), DataError> {
+    std::fs::write(path, contents).map_err(|err| DataError::Io(path.to_path_buf(), err))
+}
+
+// Image::export_png unwraps its write and flips the rows for gl framebuffers,
+// our images are stored top row first so they are encoded as they are
+pub fn export_png(image: &Image, path: &Path) -> Result<(), DataError> {
+    ::image::save_buffer_with_format(
+        path,
+        &image.bytes,
+        image.width as u32,
+        image.height as u32,
+        ::image::ColorType::Rgba8,
+        ::image::ImageFormat::Png,
+    )
+    .map_err(|err| DataError::Image(path.to_path_buf(), err.to_string()))
 }
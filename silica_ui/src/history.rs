@@ -0,0 +1,378 @@
+use std::collections::{HashMap, VecDeque};
+use std::mem::size_of;
+
+use silica_engine::{variant::Variant, world::World};
+
+// how much memory the undo stack is allowed to hold before the oldest strokes are dropped
+pub const DEFAULT_HISTORY_BUDGET: usize = 64 * 1024 * 1024;
+
+// what a cell holds, particles carry their own temperature
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CellState {
+    variant: Variant,
+    temperature: f32,
+}
+
+impl CellState {
+    fn read(world: &World, x: i32, y: i32) -> CellState {
+        let particle = world.get_particle(x, y);
+        CellState {
+            variant: particle.variant,
+            temperature: particle.temperature,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CellChange {
+    x: i32,
+    y: i32,
+    before: CellState,
+    after: CellState,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HeatChange {
+    x: i32,
+    y: i32,
+    amount: f32,
+}
+
+#[derive(Default)]
+struct Stroke {
+    cells: Vec<CellChange>,
+    heat: Vec<HeatChange>,
+}
+
+impl Stroke {
+    fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.heat.is_empty()
+    }
+
+    fn size(&self) -> usize {
+        self.cells.len() * size_of::<CellChange>() + self.heat.len() * size_of::<HeatChange>()
+    }
+}
+
+// records every cell a stroke touches so it can be undone/redone as one step
+// a stroke stays open until commit() is called, usually when the mouse is released
+pub struct History {
+    undo_stack: VecDeque<Stroke>,
+    redo_stack: Vec<Stroke>,
+    current: Stroke,
+    // index of each touched cell inside current so overlapping stamps keep the first `before`
+    touched_cells: HashMap<(i32, i32), usize>,
+    touched_heat: HashMap<(i32, i32), usize>,
+    used: usize,
+    pub budget: usize,
+}
+
+impl History {
+    pub fn new(budget: usize) -> Self {
+        History {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            current: Stroke::default(),
+            touched_cells: HashMap::new(),
+            touched_heat: HashMap::new(),
+            used: 0,
+            budget,
+        }
+    }
+
+    pub fn set_particle(&mut self, world: &mut World, x: i32, y: i32, variant: Variant) {
+        if !in_bounds(world, x, y) {
+            return;
+        }
+        let before = CellState::read(world, x, y);
+        world.set_particle(x, y, variant);
+        self.record_cell(world, x, y, before);
+    }
+
+    pub fn erase_indestructible(&mut self, world: &mut World, x: i32, y: i32) {
+        if !in_bounds(world, x, y) {
+            return;
+        }
+        let before = CellState::read(world, x, y);
+        world.erase_indestructible(x, y);
+        self.record_cell(world, x, y, before);
+    }
+
+    pub fn add_heat(&mut self, world: &mut World, x: i32, y: i32, amount: f32) {
+        if !in_bounds(world, x, y) {
+            return;
+        }
+        world.add_heat(x, y, amount);
+        match self.touched_heat.get(&(x, y)) {
+            Some(&idx) => self.current.heat[idx].amount += amount,
+            None => {
+                self.touched_heat.insert((x, y), self.current.heat.len());
+                self.current.heat.push(HeatChange { x, y, amount });
+            }
+        }
+    }
+
+    // sets the temperature of the particle at x, y, e.g. one that was just placed
+    // world.set_temperature writes the particle's own temperature, the one get_particle returns,
+    // the ambient temperature get_temperature reads only changes through add_heat
+    pub fn set_particle_temperature(
+        &mut self,
+        world: &mut World,
+        x: i32,
+        y: i32,
+        temperature: f32,
+    ) {
+        if !in_bounds(world, x, y) {
+            return;
+        }
+        let before = CellState::read(world, x, y);
+        world.set_temperature(x, y, temperature);
+        self.record_cell(world, x, y, before);
+    }
+
+    fn record_cell(&mut self, world: &World, x: i32, y: i32, before: CellState) {
+        let after = CellState::read(world, x, y);
+        match self.touched_cells.get(&(x, y)) {
+            Some(&idx) => self.current.cells[idx].after = after,
+            None => {
+                if before == after {
+                    return;
+                }
+                self.touched_cells.insert((x, y), self.current.cells.len());
+                self.current.cells.push(CellChange {
+                    x,
+                    y,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+
+    // close the open stroke and push it onto the undo stack
+    pub fn commit(&mut self) {
+        self.touched_cells.clear();
+        self.touched_heat.clear();
+        let stroke = std::mem::take(&mut self.current);
+        if stroke.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        self.push_undo(stroke);
+    }
+
+    // the engine clears the world itself, so only snapshot what is about to disappear
+    pub fn record_clear(&mut self, world: &World) {
+        self.commit();
+        let mut stroke = Stroke::default();
+        for x in 0..world.width as i32 {
+            for y in 0..world.height as i32 {
+                let before = CellState::read(world, x, y);
+                if before.variant != Variant::Empty {
+                    stroke.cells.push(CellChange {
+                        x,
+                        y,
+                        before,
+                        after: CellState {
+                            variant: Variant::Empty,
+                            temperature: before.temperature,
+                        },
+                    });
+                }
+            }
+        }
+        if !stroke.is_empty() {
+            self.redo_stack.clear();
+            self.push_undo(stroke);
+        }
+    }
+
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        self.commit();
+        let Some(stroke) = self.undo_stack.pop_back() else {
+            return false;
+        };
+        self.used -= stroke.size();
+        for change in stroke.cells.iter().rev() {
+            restore(world, change.x, change.y, change.before);
+        }
+        for change in stroke.heat.iter() {
+            if in_bounds(world, change.x, change.y) {
+                world.add_heat(change.x, change.y, -change.amount);
+            }
+        }
+        self.redo_stack.push(stroke);
+        true
+    }
+
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        self.commit();
+        let Some(stroke) = self.redo_stack.pop() else {
+            return false;
+        };
+        for change in stroke.cells.iter() {
+            restore(world, change.x, change.y, change.after);
+        }
+        for change in stroke.heat.iter() {
+            if in_bounds(world, change.x, change.y) {
+                world.add_heat(change.x, change.y, change.amount);
+            }
+        }
+        self.push_undo(stroke);
+        true
+    }
+
+    fn push_undo(&mut self, stroke: Stroke) {
+        self.used += stroke.size();
+        self.undo_stack.push_back(stroke);
+        // always keep the latest stroke, even if it alone is over budget
+        while self.used > self.budget && self.undo_stack.len() > 1 {
+            if let Some(oldest) = self.undo_stack.pop_front() {
+                self.used -= oldest.size();
+            }
+        }
+    }
+}
+
+fn in_bounds(world: &World, x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && x < world.width as i32 && y < world.height as i32
+}
+
+// walls ignore set_particle, so they have to be erased explicitly first
+fn restore(world: &mut World, x: i32, y: i32, state: CellState) {
+    if !in_bounds(world, x, y) {
+        return;
+    }
+    if world.get_particle(x, y).variant == Variant::Wall && state.variant != Variant::Wall {
+        world.erase_indestructible(x, y);
+    }
+    world.set_particle(x, y, state.variant);
+    // set_particle spawns at the default temperature, see History::set_particle_temperature
+    if state.variant != Variant::Empty {
+        world.set_temperature(x, y, state.temperature);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant_at(world: &World, x: i32, y: i32) -> Variant {
+        world.get_particle(x, y).variant
+    }
+
+    #[test]
+    fn undo_and_redo_a_stroke() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(DEFAULT_HISTORY_BUDGET);
+        history.set_particle(&mut world, 1, 1, Variant::Sand);
+        history.set_particle(&mut world, 2, 1, Variant::Sand);
+        history.commit();
+
+        assert!(history.undo(&mut world));
+        assert_eq!(variant_at(&world, 1, 1), Variant::Empty);
+        assert_eq!(variant_at(&world, 2, 1), Variant::Empty);
+
+        assert!(history.redo(&mut world));
+        assert_eq!(variant_at(&world, 1, 1), Variant::Sand);
+        assert_eq!(variant_at(&world, 2, 1), Variant::Sand);
+    }
+
+    #[test]
+    fn overlapping_stamps_undo_to_the_first_state() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(DEFAULT_HISTORY_BUDGET);
+        history.set_particle(&mut world, 3, 3, Variant::Sand);
+        history.set_particle(&mut world, 3, 3, Variant::Water);
+        history.commit();
+
+        assert!(history.undo(&mut world));
+        assert_eq!(variant_at(&world, 3, 3), Variant::Empty);
+        assert!(!history.undo(&mut world));
+    }
+
+    #[test]
+    fn undo_restores_particle_temperature() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(DEFAULT_HISTORY_BUDGET);
+        history.set_particle(&mut world, 0, 0, Variant::Sand);
+        history.set_particle_temperature(&mut world, 0, 0, 500.0);
+        history.commit();
+        history.set_particle_temperature(&mut world, 0, 0, 100.0);
+        history.commit();
+
+        assert!(history.undo(&mut world));
+        assert_eq!(world.get_particle(0, 0).temperature, 500.0);
+        assert!(history.redo(&mut world));
+        assert_eq!(world.get_particle(0, 0).temperature, 100.0);
+    }
+
+    #[test]
+    fn undo_brings_back_erased_walls() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(DEFAULT_HISTORY_BUDGET);
+        history.set_particle(&mut world, 4, 4, Variant::Wall);
+        history.commit();
+        history.erase_indestructible(&mut world, 4, 4);
+        history.commit();
+
+        assert!(history.undo(&mut world));
+        assert_eq!(variant_at(&world, 4, 4), Variant::Wall);
+        assert!(history.undo(&mut world));
+        assert_eq!(variant_at(&world, 4, 4), Variant::Empty);
+    }
+
+    #[test]
+    fn new_stroke_clears_redo() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(DEFAULT_HISTORY_BUDGET);
+        history.set_particle(&mut world, 1, 1, Variant::Sand);
+        history.commit();
+        assert!(history.undo(&mut world));
+        history.set_particle(&mut world, 2, 2, Variant::Sand);
+        history.commit();
+
+        assert!(!history.redo(&mut world));
+    }
+
+    #[test]
+    fn budget_drops_the_oldest_strokes() {
+        let mut world = World::new(8, 8);
+        // room for two single cell strokes
+        let mut history = History::new(2 * size_of::<CellChange>());
+        for x in 0..4 {
+            history.set_particle(&mut world, x, 0, Variant::Sand);
+            history.commit();
+        }
+
+        assert!(history.undo(&mut world));
+        assert!(history.undo(&mut world));
+        assert!(!history.undo(&mut world));
+        assert_eq!(variant_at(&world, 0, 0), Variant::Sand);
+        assert_eq!(variant_at(&world, 1, 0), Variant::Sand);
+        assert_eq!(variant_at(&world, 2, 0), Variant::Empty);
+    }
+
+    #[test]
+    fn latest_stroke_is_kept_over_budget() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(0);
+        history.set_particle(&mut world, 1, 1, Variant::Sand);
+        history.set_particle(&mut world, 2, 1, Variant::Sand);
+        history.commit();
+
+        assert!(history.undo(&mut world));
+        assert_eq!(variant_at(&world, 1, 1), Variant::Empty);
+    }
+
+    #[test]
+    fn edits_outside_the_world_are_ignored() {
+        let mut world = World::new(8, 8);
+        let mut history = History::new(DEFAULT_HISTORY_BUDGET);
+        history.set_particle(&mut world, -1, 0, Variant::Sand);
+        history.set_particle(&mut world, 0, 8, Variant::Sand);
+        history.commit();
+
+        assert!(!history.undo(&mut world));
+    }
+}
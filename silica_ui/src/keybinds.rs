@@ -0,0 +1,562 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use macroquad::prelude::*;
+use macroquad::ui::{hash, widgets};
+
+use crate::manager::GameProperties;
+use crate::utils::pinned_window;
+
+// everything the keyboard shortcuts in the main loop can do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Clear,
+    Pause,
+    Undo,
+    Redo,
+    NormalView,
+    HeatView,
+    PressureView,
+    Save,
+    Load,
+    NewWorld,
+    Copy,
+    Cut,
+    Paste,
+    Fullscreen,
+    // held down
+    Magnifier,
+    Keybindings,
+    // closes dialogs, stops pasting, or asks to exit
+    Back,
+}
+
+// in declaration order, Action::index relies on it
+pub const ACTIONS: [Action; 17] = [
+    Action::Clear,
+    Action::Pause,
+    Action::Undo,
+    Action::Redo,
+    Action::NormalView,
+    Action::HeatView,
+    Action::PressureView,
+    Action::Save,
+    Action::Load,
+    Action::NewWorld,
+    Action::Copy,
+    Action::Cut,
+    Action::Paste,
+    Action::Fullscreen,
+    Action::Magnifier,
+    Action::Keybindings,
+    Action::Back,
+];
+
+impl Action {
+    // key in the settings file
+    fn id(&self) -> &'static str {
+        match self {
+            Action::Clear => "clear",
+            Action::Pause => "pause",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::NormalView => "normal_view",
+            Action::HeatView => "heat_view",
+            Action::PressureView => "pressure_view",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::NewWorld => "new_world",
+            Action::Copy => "copy",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::Fullscreen => "fullscreen",
+            Action::Magnifier => "magnifier",
+            Action::Keybindings => "keybindings",
+            Action::Back => "back",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Clear => write!(f, "Clear world"),
+            Action::Pause => write!(f, "Pause"),
+            Action::Undo => write!(f, "Undo"),
+            Action::Redo => write!(f, "Redo"),
+            Action::NormalView => write!(f, "Normal view"),
+            Action::HeatView => write!(f, "Heat view"),
+            Action::PressureView => write!(f, "Pressure view"),
+            Action::Save => write!(f, "Save"),
+            Action::Load => write!(f, "Load"),
+            Action::NewWorld => write!(f, "New world"),
+            Action::Copy => write!(f, "Copy"),
+            Action::Cut => write!(f, "Cut"),
+            Action::Paste => write!(f, "Paste"),
+            Action::Fullscreen => write!(f, "Fullscreen"),
+            Action::Magnifier => write!(f, "Magnifier (hold)"),
+            Action::Keybindings => write!(f, "Keybindings"),
+            Action::Back => write!(f, "Back / exit"),
+        }
+    }
+}
+
+// keys that can be bound, also the names they are stored under
+// modifiers aren't in here, they are part of the binding instead
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Space,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+];
+
+fn ctrl_down() -> bool {
+    is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl)
+}
+
+fn shift_down() -> bool {
+    is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift)
+}
+
+// a key with the modifiers that have to be held with it, extra modifiers don't match
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl KeyBinding {
+    const fn key(key: KeyCode) -> KeyBinding {
+        KeyBinding {
+            key,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    const fn ctrl(key: KeyCode) -> KeyBinding {
+        KeyBinding {
+            key,
+            ctrl: true,
+            shift: false,
+        }
+    }
+
+    fn modifiers_match(&self) -> bool {
+        self.ctrl == ctrl_down() && self.shift == shift_down()
+    }
+
+    pub fn pressed(&self) -> bool {
+        is_key_pressed(self.key) && self.modifiers_match()
+    }
+
+    pub fn down(&self) -> bool {
+        is_key_down(self.key) && self.modifiers_match()
+    }
+
+    // "Ctrl+Shift+Z", the same text is written to the settings file
+    fn parse(text: &str) -> Option<KeyBinding> {
+        let mut binding = KeyBinding::key(KeyCode::Unknown);
+        for part in text.split('+').map(|part| part.trim()) {
+            match part {
+                "Ctrl" => binding.ctrl = true,
+                "Shift" => binding.shift = true,
+                name => {
+                    binding.key = *BINDABLE_KEYS
+                        .iter()
+                        .find(|key| format!("{:?}", key) == name)?;
+                }
+            }
+        }
+        (binding.key != KeyCode::Unknown).then_some(binding)
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+// one binding per action in ACTIONS order, None leaves the action without a key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBindings([Option<KeyBinding>; ACTIONS.len()]);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut bindings = KeyBindings([None; ACTIONS.len()]);
+        for (action, binding) in [
+            (Action::Clear, KeyBinding::key(KeyCode::R)),
+            (Action::Pause, KeyBinding::key(KeyCode::Space)),
+            (Action::Undo, KeyBinding::ctrl(KeyCode::Z)),
+            (
+                Action::Redo,
+                KeyBinding {
+                    key: KeyCode::Z,
+                    ctrl: true,
+                    shift: true,
+                },
+            ),
+            (Action::NormalView, KeyBinding::key(KeyCode::Key1)),
+            (Action::HeatView, KeyBinding::key(KeyCode::Key2)),
+            (Action::PressureView, KeyBinding::key(KeyCode::Key3)),
+            (Action::Save, KeyBinding::ctrl(KeyCode::S)),
+            (Action::Load, KeyBinding::ctrl(KeyCode::L)),
+            (Action::NewWorld, KeyBinding::ctrl(KeyCode::N)),
+            (Action::Copy, KeyBinding::ctrl(KeyCode::C)),
+            (Action::Cut, KeyBinding::ctrl(KeyCode::X)),
+            (Action::Paste, KeyBinding::ctrl(KeyCode::V)),
+            (Action::Fullscreen, KeyBinding::key(KeyCode::F11)),
+            (Action::Magnifier, KeyBinding::key(KeyCode::Z)),
+            (Action::Keybindings, KeyBinding::ctrl(KeyCode::K)),
+            (Action::Back, KeyBinding::key(KeyCode::Escape)),
+        ] {
+            bindings.set(action, Some(binding));
+        }
+        bindings
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: Action) -> Option<KeyBinding> {
+        self.0[action.index()]
+    }
+
+    // a key can only do one thing, whatever had it before is left unbound
+    // Back always keeps a key, it is the only way to reach the exit dialog, so it can't be
+    // unbound and its key can't be given to another action
+    pub fn set(&mut self, action: Action, binding: Option<KeyBinding>) {
+        let back = self.get(Action::Back);
+        if action == Action::Back && binding.is_none() {
+            return;
+        }
+        if action != Action::Back && back.is_some() && binding == back {
+            return;
+        }
+        if binding.is_some() {
+            for other in self.0.iter_mut() {
+                if *other == binding {
+                    *other = None;
+                }
+            }
+        }
+        self.0[action.index()] = binding;
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.get(action).is_some_and(|binding| binding.pressed())
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.get(action).is_some_and(|binding| binding.down())
+    }
+
+    // key.<action>=<binding> lines, missing or unreadable ones keep the default
+    // a key bound twice in the file goes to the later action, like it would through set
+    pub fn read(values: &HashMap<String, String>) -> KeyBindings {
+        let mut bindings = KeyBindings::default();
+        for action in ACTIONS {
+            match values
+                .get(&format!("key.{}", action.id()))
+                .map(|v| v.trim())
+            {
+                Some("none") => bindings.set(action, None),
+                Some(text) => {
+                    if let Some(binding) = KeyBinding::parse(text) {
+                        bindings.set(action, Some(binding));
+                    }
+                }
+                None => (),
+            }
+        }
+        bindings
+    }
+}
+
+impl Display for KeyBindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for action in ACTIONS {
+            match self.get(action) {
+                Some(binding) => writeln!(f, "key.{}={}", action.id(), binding)?,
+                None => writeln!(f, "key.{}=none", action.id())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// lists every action with its key, capturing is the action waiting for a new key
+pub fn draw_keybindings_dialog(
+    bindings: &mut KeyBindings,
+    capturing: &mut Option<Action>,
+    props: &mut GameProperties,
+) {
+    let dialog_width = 520.0;
+    let row_height = 28.0;
+    let button_height = 40.0;
+    let dialog_height = ACTIONS.len() as f32 * row_height + button_height + 60.0;
+
+    // the next bindable key pressed while capturing becomes the binding, modifiers included
+    // escape cancels instead, so a capture started by mistake can be left
+    if let Some(action) = *capturing {
+        if let Some(key) = get_last_key_pressed().filter(|key| BINDABLE_KEYS.contains(key)) {
+            if key != KeyCode::Escape {
+                bindings.set(
+                    action,
+                    Some(KeyBinding {
+                        key,
+                        ctrl: ctrl_down(),
+                        shift: shift_down(),
+                    }),
+                );
+            }
+            *capturing = None;
+        }
+    }
+
+    pinned_window(
+        hash!(),
+        vec2(
+            (screen_width() - dialog_width) / 2.0,
+            (screen_height() - dialog_height) / 2.0,
+        ),
+        vec2(dialog_width, dialog_height),
+        |ui| {
+            for (row, action) in ACTIONS.iter().enumerate() {
+                let y = row as f32 * row_height;
+                ui.label(Some(vec2(10.0, y)), &action.to_string());
+                let key = if *capturing == Some(*action) {
+                    "press a key...".to_string()
+                } else {
+                    bindings
+                        .get(*action)
+                        .map_or("-".to_string(), |binding| binding.to_string())
+                };
+                ui.label(Some(vec2(200.0, y)), &key);
+
+                if widgets::Button::new("CHANGE")
+                    .position(vec2(dialog_width - 200.0, y))
+                    .size(vec2(90.0, row_height - 4.0))
+                    .ui(ui)
+                {
+                    *capturing = Some(*action);
+                }
+                if *action != Action::Back
+                    && widgets::Button::new("UNBIND")
+                        .position(vec2(dialog_width - 105.0, y))
+                        .size(vec2(90.0, row_height - 4.0))
+                        .ui(ui)
+                {
+                    bindings.set(*action, None);
+                    *capturing = None;
+                }
+            }
+
+            let y = dialog_height - button_height - 20.0;
+            if widgets::Button::new("Defaults")
+                .position(vec2(10.0, y))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                *bindings = KeyBindings::default();
+                *capturing = None;
+            }
+            if widgets::Button::new("Close")
+                .position(vec2(dialog_width / 2.0, y))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                props.requested_keybindings = false;
+                *capturing = None;
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::parse_values;
+
+    #[test]
+    fn bindings_round_trip_through_text() {
+        let bindings = [
+            KeyBinding::key(KeyCode::Space),
+            KeyBinding::ctrl(KeyCode::Z),
+            KeyBinding {
+                key: KeyCode::Z,
+                ctrl: true,
+                shift: true,
+            },
+            KeyBinding {
+                key: KeyCode::F11,
+                ctrl: false,
+                shift: true,
+            },
+        ];
+        for binding in bindings {
+            assert_eq!(KeyBinding::parse(&binding.to_string()), Some(binding));
+        }
+        assert_eq!(KeyBinding::ctrl(KeyCode::Z).to_string(), "Ctrl+Z");
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys() {
+        assert_eq!(KeyBinding::parse("Ctrl+Nope"), None);
+        assert_eq!(KeyBinding::parse("Ctrl"), None);
+        assert_eq!(KeyBinding::parse(""), None);
+        assert_eq!(KeyBinding::parse("LeftShift"), None);
+    }
+
+    #[test]
+    fn actions_are_listed_in_declaration_order() {
+        for (idx, action) in ACTIONS.iter().enumerate() {
+            assert_eq!(action.index(), idx);
+        }
+    }
+
+    #[test]
+    fn defaults_bind_every_action() {
+        let bindings = KeyBindings::default();
+        for action in ACTIONS {
+            assert!(bindings.get(action).is_some(), "{} has no key", action);
+        }
+        assert_eq!(
+            bindings.get(Action::Magnifier),
+            Some(KeyBinding::key(KeyCode::Z))
+        );
+        assert_eq!(
+            bindings.get(Action::Undo),
+            Some(KeyBinding::ctrl(KeyCode::Z))
+        );
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let mut bindings = KeyBindings::default();
+        bindings.set(Action::Clear, None);
+        bindings.set(Action::Pause, Some(KeyBinding::key(KeyCode::P)));
+        let values = parse_values(&bindings.to_string());
+        assert_eq!(KeyBindings::read(&values), bindings);
+    }
+
+    #[test]
+    fn a_key_bound_twice_goes_to_the_later_action() {
+        let values = parse_values("key.clear=P\nkey.pause=P\n");
+        let bindings = KeyBindings::read(&values);
+        assert_eq!(bindings.get(Action::Clear), None);
+        assert_eq!(
+            bindings.get(Action::Pause),
+            Some(KeyBinding::key(KeyCode::P))
+        );
+    }
+
+    #[test]
+    fn back_always_keeps_a_key() {
+        let mut bindings = KeyBindings::default();
+        bindings.set(Action::Back, None);
+        bindings.set(Action::Clear, Some(KeyBinding::key(KeyCode::Escape)));
+        assert_eq!(
+            bindings.get(Action::Back),
+            Some(KeyBinding::key(KeyCode::Escape))
+        );
+        assert_eq!(
+            bindings.get(Action::Clear),
+            Some(KeyBinding::key(KeyCode::R))
+        );
+
+        let values = parse_values("key.back=none\n");
+        assert!(KeyBindings::read(&values).get(Action::Back).is_some());
+    }
+}
@@ -1,44 +1,105 @@
+mod autosave;
+mod brush;
+mod camera;
+mod clipboard;
+mod colormap;
 mod data;
+mod history;
+mod keybinds;
 mod manager;
+mod meta;
+mod new_world;
+mod saves;
+mod settings;
+mod shapes;
+mod stamps;
+mod thumbnails;
+mod toast;
 mod utils;
 
-use data::{create_data_dir, get_save_dir};
-use macroquad::miniquad::window::{request_quit, screen_size};
+use autosave::{draw_restore_dialog, latest_autosave, start_session, Autosaver};
+use brush::{BrushCache, BrushShape, Spray};
+use camera::Camera;
+use clipboard::{
+    draw_clipboard_panel, draw_paste_preview, draw_selection, handle_clipboard_action, Clipboard,
+    ClipboardAction, Region,
+};
+use colormap::{draw_heat_legend, HeatScale};
+use data::{create_data_dir, get_save_dir, get_stamp_dir, path_str};
+use history::History;
+use keybinds::{draw_keybindings_dialog, Action};
+use macroquad::miniquad::window::request_quit;
 use macroquad::prelude::*;
 
 use macroquad::ui::{hash, root_ui, widgets, Skin, Style};
-use manager::{GameProperties, Property, RenderMode, Tool, WorldInfo};
+use manager::{GameProperties, PaintFilter, Property, RenderMode, Shape, Tool, WorldInfo};
+use meta::{
+    default_author, file_modified, format_timestamp, matches_query, now, MetaCache, SaveMeta,
+    SaveSort, SAVE_SORTS,
+};
+use new_world::{draw_new_world_dialog, EdgeMode, NewWorldOptions};
 use rayon::prelude::*;
+use saves::{draw_save_actions, save_exists, write_save, SaveActions};
+use settings::Settings;
+use shapes::{apply_shape, draw_shape_preview, flood_fill};
 use silica_engine::group::ElementManager;
 use silica_engine::prelude::*;
+use stamps::{draw_stamp_browser, draw_stamp_save_dialog, load_stamp, save_stamp};
+use thumbnails::ThumbnailCache;
+use toast::Toasts;
 use utils::*;
 
 const UI_OFFSET_X: f32 = 50.0;
 const UI_OFFSET_Y: f32 = 60.;
+// cells sampled across the view when fitting the heat scale, views up to this wide are read fully
+const AUTO_RANGE_SAMPLES: f32 = 1024.0;
+// seconds the settings have to stay the same before they are written, wheel ticks and resizes come in bursts
+const SETTINGS_SAVE_DELAY: f64 = 0.5;
 
 fn window_conf() -> Conf {
+    let (window_width, window_height) = Settings::load_window_size();
     Conf {
         window_title: "Silica".to_owned(),
-        window_width: 1390,
-        window_height: 900,
+        window_width,
+        window_height,
         fullscreen: false,
 
-        window_resizable: false,
+        window_resizable: true,
         ..Default::default()
     }
 }
 
-const TOOLS: [Property; 4] = [
+const TOOLS: [Property; 5] = [
     Property::Temperature,
     Property::COOL,
     Property::Pressure,
+    Property::SetTemp,
     Property::DelWall,
 ];
 
+const BRUSHES: [BrushShape; 6] = [
+    BrushShape::Circle,
+    BrushShape::Square,
+    BrushShape::Diamond,
+    BrushShape::HorizontalLine,
+    BrushShape::VerticalLine,
+    BrushShape::Custom,
+];
+
+const SHAPES: [Shape; 5] = [
+    Shape::Line,
+    Shape::Rect,
+    Shape::FilledRect,
+    Shape::Ellipse,
+    Shape::Fill,
+];
+
 #[macroquad::main(window_conf)]
 async fn main() {
     prevent_quit();
-    create_data_dir();
+    let mut toasts = Toasts::default();
+    toasts.report(create_data_dir());
+    let crashed = toasts.report(start_session()).unwrap_or(false);
     let label_style: Style = root_ui()
         .style_builder()
         .font(include_bytes!("./fonts/standard.ttf"))
@@ -68,24 +129,67 @@ async fn main() {
     let h: usize = 383;
     let mut image = Image::gen_image_color(w as u16, h as u16, color_u8!(13, 16, 20, 1));
     let mut world: World = World::new(w as i32, h as i32);
-    let texture = Texture2D::from_image(&image);
+    let mut texture = Texture2D::from_image(&image);
     texture.set_filter(FilterMode::Nearest);
 
     let mut chosen_name = String::new();
+    // title, description and author typed into the save dialog
+    let mut save_meta = SaveMeta {
+        author: default_author(),
+        ..Default::default()
+    };
+    let mut stamp_name = String::new();
     let mut filter_name = String::new();
 
+    let element_manager: ElementManager = ElementManager::new();
+    register_element_groups(&element_manager);
+
+    let settings = Settings::load(&element_manager);
+    let mut saved_settings = settings;
+    // settings that differ from the file, with the time they last changed
+    let mut unsaved_settings: Option<(Settings, f64)> = None;
+
     let mut game_properties = GameProperties {
-        tool_radius: 10.0,
-        tool_type: Tool::ElementTool(Variant::Sand),
-        selected_group_idx: 0,
+        tool_radius: settings.tool_radius,
+        tool_type: settings.tool,
+        brush_shape: BrushShape::Circle,
+        custom_brush_idx: 0,
+        spray_density: 100.0,
+        paint_filter: PaintFilter::All,
+        erase_filter: PaintFilter::All,
+        heat_settings: settings.heat,
+        cool_settings: settings.cool,
+        pressure_settings: settings.pressure,
+        set_temperature: 20.0,
+        show_tool_options: false,
+        paint_at_temperature: false,
+        paint_temperature: 20.0,
+        autosave_interval: settings.autosave_interval,
+        selected_group_idx: settings.group_idx,
         hovering_over: EMPTY_CELL,
         hovering_temperature: 0.0,
         left_mouse_down: false,
         right_mouse_down: false,
-        render_mode: RenderMode::Normal,
+        last_mouse_world: None,
+        dragging_ui: false,
+        pan_anchor: None,
+        panned: false,
+        shape: Shape::Freehand,
+        shape_start: None,
+        selection: None,
+        selecting: false,
+        pasting: false,
+        render_mode: settings.render_mode,
+        heat_scale: HeatScale::DEFAULT,
         requested_exit: false,
         requested_save: false,
         requested_load: false,
+        requested_stamp_save: false,
+        requested_stamp_load: false,
+        requested_new: false,
+        requested_restore: crashed && latest_autosave().is_some(),
+        requested_keybindings: false,
+        edge_mode: EdgeMode::Solid,
     };
     let mut world_info = WorldInfo {
         fps: 0.,
@@ -93,25 +197,71 @@ async fn main() {
         world_width: w,
         world_height: h,
         parts: 0,
+        camera: Camera::default(),
     };
 
     draw_walls(&mut world);
+    let mut history = History::new(settings.history_budget);
+    let mut spray = Spray::default();
+    let mut clipboard = Clipboard::default();
+    let mut brushes = BrushCache::default();
+    let mut temperature_inputs = TemperatureInputs::new(&world_info.properties);
+    let mut fullscreen = false;
+    let mut new_world_options = NewWorldOptions::new(w, h);
+    // without a data dir the caches stay empty, the error was already shown
+    let mut save_thumbnails = ThumbnailCache::new(get_save_dir().unwrap_or_default(), 160);
+    let mut save_metas = MetaCache::default();
+    let mut save_sort = SaveSort::Name;
+    let mut save_actions = SaveActions::default();
+    // name the user already agreed to overwrite in the save dialog
+    let mut overwrite_name: Option<String> = None;
+    let mut stamp_thumbnails = ThumbnailCache::new(get_stamp_dir().unwrap_or_default(), 96);
+    let mut last_autosave = get_time();
+    let mut autosaver = Autosaver::new();
+    let mut keybindings = settings.keybindings;
+    // action on the keybindings screen waiting for its new key
+    let mut keybinding_capture = None;
 
-    let element_manager: ElementManager = ElementManager::new();
-    register_element_groups(&element_manager);
     root_ui().push_skin(&skin);
 
     loop {
         clear_background(BLACK);
-        let can_draw = !game_properties.requested_exit
-            && !game_properties.requested_save
-            && !game_properties.requested_load;
+        let can_draw = !game_properties.is_dialog_open();
+
+        // a new or loaded world can have another size, the image and texture have to follow it
+        if image.width() != world.width as usize || image.height() != world.height as usize {
+            image = Image::gen_image_color(
+                world.width as u16,
+                world.height as u16,
+                color_u8!(13, 16, 20, 1),
+            );
+            texture = Texture2D::from_image(&image);
+            texture.set_filter(FilterMode::Nearest);
+            world_info.world_width = world.width as usize;
+            world_info.world_height = world.height as usize;
+            world_info.camera = Camera::default();
+        }
 
-        if is_key_pressed(KeyCode::Escape) {
+        // while the keybindings screen waits for a key, that key is only bound
+        if keybinding_capture.is_none() && keybindings.pressed(Action::Back) {
             // we could be loading, saving, or trying to exit
-            if game_properties.requested_load || game_properties.requested_save {
+            if game_properties.requested_load
+                || game_properties.requested_save
+                || game_properties.requested_stamp_save
+                || game_properties.requested_stamp_load
+                || game_properties.requested_new
+                || game_properties.requested_restore
+                || game_properties.requested_keybindings
+            {
                 game_properties.requested_load = false;
                 game_properties.requested_save = false;
+                game_properties.requested_stamp_save = false;
+                game_properties.requested_stamp_load = false;
+                game_properties.requested_new = false;
+                game_properties.requested_restore = false;
+                game_properties.requested_keybindings = false;
+            } else if world_info.properties.pasting {
+                world_info.properties.pasting = false;
             } else {
                 game_properties.requested_exit = !game_properties.requested_exit;
             }
@@ -132,13 +282,14 @@ async fn main() {
         let h = image.height();
 
         let mouse_pos = mouse_position();
-        let mouse_x = mouse_pos.0 as usize;
-        let mouse_y = mouse_pos.1 as usize;
 
         // convert screen coords to world coords for mouse
-        let (screen_w, screen_h) = screen_size();
-        let mouse_x_world = (mouse_x as f32 / (screen_w - UI_OFFSET_X) * w as f32) as usize;
-        let mouse_y_world = (mouse_y as f32 / (screen_h - UI_OFFSET_Y) * h as f32) as usize;
+        let (mouse_x_world, mouse_y_world) = screen_to_world(mouse_pos.0, mouse_pos.1, &world_info);
+        let mouse_x_world = mouse_x_world as usize;
+        let mouse_y_world = mouse_y_world as usize;
+        // the letterbox bars around the world texture don't count as world
+        let world_view = world_view_rect(w, h);
+        let in_world_view = world_view.contains(vec2(mouse_pos.0, mouse_pos.1));
 
         /*
         for x in 0..w as u32 {
@@ -154,13 +305,50 @@ async fn main() {
 
         // use parallel iterator to speed up rendering
 
+        // a clear takes effect whichever map is being shown
+        if world.cleared && can_draw {
+            world.reset();
+        }
+
         if game_properties.render_mode == RenderMode::Heat {
             // draw temperature map of World environment
+            let cell_temp = |x: u32, y: u32| {
+                world.get_temperature(x as i32, y as i32)
+                    + world.get_particle(x as i32, y as i32).temperature
+            };
+            if world_info.properties.heat_scale.auto_range {
+                // only the part on screen sets the range, zoomed out views are sampled
+                // at most AUTO_RANGE_SAMPLES cells across
+                let visible = world_info.camera.visible_rect(w, h);
+                let step = ((visible.w.max(visible.h) / AUTO_RANGE_SAMPLES).ceil() as usize).max(1);
+                let x_end = (visible.right().ceil() as u32).min(w as u32);
+                let y_end = (visible.bottom().ceil() as u32).min(h as u32);
+                let mut min = f32::MAX;
+                let mut max = f32::MIN;
+                for x in (visible.x as u32..x_end).step_by(step) {
+                    for y in (visible.y as u32..y_end).step_by(step) {
+                        let temp = cell_temp(x, y);
+                        min = min.min(temp);
+                        max = max.max(temp);
+                    }
+                }
+                world_info.properties.heat_scale.fit(min, max);
+            }
+            let scale = world_info.properties.heat_scale;
             for x in 0..w as u32 {
                 for y in 0..h as u32 {
-                    let temp = world.get_temperature(x as i32, y as i32);
-                    let part_temp = world.get_particle(x as i32, y as i32).temperature;
-                    let color = temp_to_color(temp + part_temp);
+                    let color = scale.color(cell_temp(x, y));
+                    let c = color_u8!(color.0, color.1, color.2, 255);
+
+                    image.set_pixel(x, y, c);
+                }
+            }
+        } else if game_properties.render_mode == RenderMode::Pressure {
+            // draw pressure map, blue is below zero and red above
+            for x in 0..w as u32 {
+                for y in 0..h as u32 {
+                    let pressure = world.get_pressure(x as i32, y as i32);
+                    let color = pressure_to_color(pressure);
                     let c = color_u8!(color.0, color.1, color.2, 255);
 
                     image.set_pixel(x, y, c);
@@ -181,9 +369,7 @@ async fn main() {
                 });
 
             /* */
-            if world.cleared && can_draw {
-                world.reset();
-            } else if !world.modified_indices.is_empty() {
+            if !world.modified_indices.is_empty() {
                 // go through modified indices and only update those on the image
                 for idx in world.modified_indices.iter() {
                     let x = idx % w as usize;
@@ -199,9 +385,7 @@ async fn main() {
         }
 
         world_info.parts = world.get_particle_count();
-        if mouse_position().0 < screen_w - UI_OFFSET_X
-            && mouse_position().1 < screen_h - UI_OFFSET_Y
-        {
+        if in_world_view {
             let particle = world.get_particle(mouse_x_world as i32, mouse_y_world as i32);
             world_info.properties.hovering_over = particle;
             world_info.properties.hovering_temperature =
@@ -209,67 +393,242 @@ async fn main() {
         }
         if game_properties.left_mouse_down || game_properties.right_mouse_down {}
 
-        if game_properties.requested_exit
-            || game_properties.requested_save
-            || game_properties.requested_load
-        {
+        if game_properties.is_dialog_open() {
             // don't update the world
             world.pause();
         } else {
             world.resume();
         }
         world.tick();
+        if game_properties.edge_mode == EdgeMode::Void {
+            clear_edges(&mut world);
+        }
+
+        // rotating snapshots for crash recovery, held back while the restore prompt may still need them
+        let autosave_interval = game_properties.autosave_interval as f64;
+        if autosave_interval > 0.0
+            && get_time() - last_autosave >= autosave_interval
+            && !game_properties.requested_restore
+        {
+            last_autosave = get_time();
+            autosaver.save(&world);
+        }
+        if let Some(result) = autosaver.poll() {
+            toasts.report(result);
+        }
 
         let mouse_wheel = mouse_wheel().1;
         //world_info.properties.tool_radius += mouse_wheel;
-        // use logarithmic scale for radius change
-        if mouse_wheel > 0.0 {
+        // ctrl + wheel zooms around the cursor, the plain wheel resizes the brush
+        let zooming = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if zooming && mouse_wheel != 0.0 {
+            let factor = if mouse_wheel > 0.0 { 1.25 } else { 1.0 / 1.25 };
+            let (x, y) = screen_to_world(mouse_pos.0, mouse_pos.1, &world_info);
+            world_info.camera.zoom_at(factor, x, y, w, h);
+        } else if mouse_wheel > 0.0 {
+            // use logarithmic scale for radius change
             world_info.properties.tool_radius *= 1.1;
         } else if mouse_wheel < 0.0 {
             world_info.properties.tool_radius /= 1.1;
         }
+        // a brush wider than the world covers nothing more and takes long to build
+        world_info.properties.tool_radius = world_info
+            .properties
+            .tool_radius
+            .min(world.width.max(world.height) as f32);
+        let brush = brushes.get(
+            world_info.properties.brush_shape,
+            world_info.properties.tool_radius as i32,
+            world_info.properties.spray_density,
+            world_info.properties.custom_brush_idx,
+        );
+
         // handle input
-        if can_draw
-            && game_properties.left_mouse_down
-            && mouse_y < screen_h as usize - UI_OFFSET_Y as usize
-            && mouse_x < screen_w as usize - UI_OFFSET_X as usize
-        {
-            // use screen coords mapped to world coords
-            // make sure that the particle at the mouse position is empty
+        let mouse_world = (mouse_x_world as i32, mouse_y_world as i32);
+        // stamp along the segment since last frame so fast drags don't leave gaps
+        let (from_x, from_y) = game_properties.last_mouse_world.unwrap_or(mouse_world);
+        let spacing = stroke_spacing(world_info.properties.tool_radius);
+        let over_ui = root_ui().is_mouse_over(vec2(mouse_pos.0, mouse_pos.1));
+        let clicked_world =
+            can_draw && in_world_view && !over_ui && is_mouse_button_pressed(MouseButton::Left);
+        // middle drag pans the view, a middle click that doesn't move picks the element under the cursor
+        if can_draw && in_world_view && !over_ui && is_mouse_button_pressed(MouseButton::Middle) {
+            game_properties.pan_anchor =
+                Some(screen_to_world(mouse_pos.0, mouse_pos.1, &world_info));
+            game_properties.panned = false;
+        }
+        if let Some((anchor_x, anchor_y)) = game_properties.pan_anchor {
+            let (cursor_x, cursor_y) = screen_to_world(mouse_pos.0, mouse_pos.1, &world_info);
+            if cursor_x.floor() != anchor_x.floor() || cursor_y.floor() != anchor_y.floor() {
+                game_properties.panned = true;
+            }
+            world_info.camera.grab(
+                anchor_x,
+                anchor_y,
+                (mouse_pos.0 - world_view.x) / world_view.w,
+                (mouse_pos.1 - world_view.y) / world_view.h,
+                w,
+                h,
+            );
+            if !is_mouse_button_down(MouseButton::Middle) {
+                if !game_properties.panned {
+                    let hovered = world_info.properties.hovering_over.variant;
+                    pick_element(&element_manager, &mut world_info.properties, hovered);
+                }
+                game_properties.pan_anchor = None;
+            }
+        }
 
-            if let Tool::ElementTool(_) = world_info.properties.tool_type {
-                use_tool(
-                    world_info.properties,
-                    &mut world,
-                    mouse_x_world as i32,
-                    mouse_y_world as i32,
-                );
-            } else {
-                // If the tool is not an ElementTool, use the tool directly
-                use_tool(
-                    world_info.properties,
-                    &mut world,
-                    mouse_x_world as i32,
-                    mouse_y_world as i32,
-                );
+        // drags that start on a panel (like the density slider) must not paint
+        if is_mouse_button_pressed(MouseButton::Left) {
+            game_properties.dragging_ui = over_ui;
+        } else if !game_properties.left_mouse_down {
+            game_properties.dragging_ui = false;
+        }
+        if world_info.properties.pasting {
+            if let Some(region) = &clipboard.region {
+                if clicked_world {
+                    let origin = region.origin_centered_on(mouse_world.0, mouse_world.1);
+                    region.paste(&mut world, &mut history, origin);
+                    history.commit();
+                }
+            }
+            if is_mouse_button_pressed(MouseButton::Right) {
+                world_info.properties.pasting = false;
+            }
+        } else if world_info.properties.tool_type == Tool::PickerTool {
+            if clicked_world {
+                let hovered = world_info.properties.hovering_over.variant;
+                pick_element(&element_manager, &mut world_info.properties, hovered);
             }
+        } else if world_info.properties.tool_type == Tool::SelectTool {
+            if clicked_world {
+                world_info.properties.selection = Some((mouse_world, mouse_world));
+                world_info.properties.selecting = true;
+            }
+            if world_info.properties.selecting {
+                if let Some((start, _)) = world_info.properties.selection {
+                    world_info.properties.selection = Some((start, mouse_world));
+                }
+                world_info.properties.selecting = game_properties.left_mouse_down;
+            }
+            if is_mouse_button_pressed(MouseButton::Right) {
+                world_info.properties.selection = None;
+            }
+        } else {
+            match world_info.properties.shape {
+                Shape::Freehand => {
+                    if can_draw
+                        && game_properties.left_mouse_down
+                        && in_world_view
+                        && !game_properties.dragging_ui
+                    {
+                        // use screen coords mapped to world coords
+                        stroke_line(
+                            from_x,
+                            from_y,
+                            mouse_world.0,
+                            mouse_world.1,
+                            spacing,
+                            |x, y| {
+                                use_tool(
+                                    world_info.properties,
+                                    &mut world,
+                                    &mut history,
+                                    &mut spray,
+                                    brush,
+                                    x,
+                                    y,
+                                )
+                            },
+                        );
+                    }
+                }
+                Shape::Fill => {
+                    if clicked_world {
+                        if let Tool::ElementTool(variant) = world_info.properties.tool_type {
+                            flood_fill(
+                                &mut world,
+                                &mut history,
+                                mouse_world.0,
+                                mouse_world.1,
+                                variant,
+                                world_info.properties.spawn_temperature(),
+                            );
+                        }
+                    }
+                }
+                _ => {
+                    // press-drag-release, right click cancels the drag
+                    if clicked_world {
+                        world_info.properties.shape_start = Some(mouse_world);
+                    }
+                    if is_mouse_button_pressed(MouseButton::Right) {
+                        world_info.properties.shape_start = None;
+                    }
+                    if !game_properties.left_mouse_down {
+                        if let Some(start) = world_info.properties.shape_start.take() {
+                            apply_shape(
+                                world_info.properties,
+                                &mut world,
+                                &mut history,
+                                &mut spray,
+                                brush,
+                                start,
+                                mouse_world,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        if !game_properties.left_mouse_down && !game_properties.right_mouse_down {
+            // the stroke ends when every button is released
+            history.commit();
+            spray.end_stroke();
         }
 
-        if game_properties.right_mouse_down {
-            erase_radius(
-                &mut world,
-                mouse_x_world as i32,
-                mouse_y_world as i32,
-                world_info.properties.tool_radius as i32,
+        // like painting, erasing only happens over the world and not through dialogs or panels
+        let erasing = game_properties.right_mouse_down
+            && can_draw
+            && in_world_view
+            && !over_ui
+            && !world_info.properties.pasting
+            && world_info.properties.tool_type != Tool::SelectTool;
+        if erasing {
+            stroke_line(
+                from_x,
+                from_y,
+                mouse_world.0,
+                mouse_world.1,
+                spacing,
+                |x, y| {
+                    erase_radius(
+                        &mut world,
+                        &mut history,
+                        x,
+                        y,
+                        brush,
+                        world_info.properties.erase_filter,
+                    )
+                },
             );
         }
 
+        game_properties.last_mouse_world =
+            if game_properties.left_mouse_down || game_properties.right_mouse_down {
+                Some(mouse_world)
+            } else {
+                None
+            };
+
         // check if mouse is moving
         #[cfg(target_arch = "wasm32")]
         for touch in touches() {
             world.resume();
 
-            if touch.position.y < screen_h - 60. && touch.position.x < screen_w - 200. {
+            if world_view.contains(touch.position) {
                 // use screen coords mapped to world coords
                 // make sure that the particle at the mouse position is empty
                 if world
@@ -280,6 +639,9 @@ async fn main() {
                     use_tool(
                         world_info.properties,
                         &mut world,
+                        &mut history,
+                        &mut spray,
+                        brush,
                         touch.position.x as i32,
                         touch.position.y as i32,
                     );
@@ -287,37 +649,92 @@ async fn main() {
             }
         }
 
-        // if z is pressed, draw a zoomed in version of the world at the mouse position inside a rect
+        // shortcuts are off in dialogs and temperature boxes so typing doesn't clear the world
+        let hotkeys = !game_properties.is_dialog_open() && !temperature_inputs.editing;
 
-        if is_key_pressed(KeyCode::R) {
+        if hotkeys && keybindings.pressed(Action::Clear) {
+            history.record_clear(&world);
             world.cleared = true;
         }
 
-        if is_key_pressed(KeyCode::Space) {
+        if hotkeys && keybindings.pressed(Action::Undo) {
+            history.undo(&mut world);
+        }
+        if hotkeys && keybindings.pressed(Action::Redo) {
+            history.redo(&mut world);
+        }
+
+        if hotkeys && keybindings.pressed(Action::Pause) {
             world.running = !world.running;
         }
 
-        if let Some(key) = get_last_key_pressed() {
-            match key {
-                KeyCode::Key1 => {
-                    game_properties.render_mode = RenderMode::Normal;
-                }
+        if keybinding_capture.is_none() && keybindings.pressed(Action::Fullscreen) {
+            fullscreen = !fullscreen;
+            set_fullscreen(fullscreen);
+        }
 
-                KeyCode::Key2 => {
-                    game_properties.render_mode = RenderMode::Heat;
-                }
+        if hotkeys {
+            if keybindings.pressed(Action::NormalView) {
+                game_properties.render_mode = RenderMode::Normal;
+            }
+            if keybindings.pressed(Action::HeatView) {
+                game_properties.render_mode = RenderMode::Heat;
+            }
+            if keybindings.pressed(Action::PressureView) {
+                game_properties.render_mode = RenderMode::Pressure;
+            }
+        }
 
-                _ => (),
+        // copy or cut the selection, paste starts placing the clipboard
+        let mut clipboard_action = None;
+        if hotkeys {
+            if keybindings.pressed(Action::Copy) {
+                clipboard_action = Some(ClipboardAction::Copy);
+            } else if keybindings.pressed(Action::Cut) {
+                clipboard_action = Some(ClipboardAction::Cut);
+            } else if keybindings.pressed(Action::Paste) {
+                clipboard_action = Some(ClipboardAction::Paste);
             }
         }
 
-        // CTRL + S to save
-        if is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::S) {
+        if hotkeys && keybindings.pressed(Action::Save) {
             game_properties.requested_save = true;
         }
 
-        if is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::L) {
+        if hotkeys && keybindings.pressed(Action::Load) {
             game_properties.requested_load = true;
+            // sidecars could have changed since the dialog was last open
+            save_metas.clear();
+            save_actions = SaveActions::default();
+        }
+
+        if hotkeys && keybindings.pressed(Action::NewWorld) {
+            game_properties.requested_new = true;
+        }
+
+        if hotkeys && keybindings.pressed(Action::Keybindings) {
+            game_properties.requested_keybindings = true;
+        }
+
+        if game_properties.requested_keybindings {
+            draw_keybindings_dialog(
+                &mut keybindings,
+                &mut keybinding_capture,
+                &mut game_properties,
+            );
+        }
+
+        if game_properties.requested_new
+            && draw_new_world_dialog(&mut new_world_options, &mut game_properties)
+        {
+            if let Some((width, height)) = new_world_options.size() {
+                world = World::new(width as i32, height as i32);
+                if new_world_options.border {
+                    draw_walls(&mut world);
+                }
+                game_properties.edge_mode = new_world_options.edge_mode;
+                forget_world_edits(&mut history, &mut world_info.properties);
+            }
         }
 
         if game_properties.requested_load {
@@ -332,7 +749,8 @@ async fn main() {
             let cancel_button_position =
                 vec2((dialog_width / 2.0), dialog_height - button_height - 20.0); // Adjust the position of the Cancel button
 
-            root_ui().window(
+            save_thumbnails.update();
+            pinned_window(
                 hash!(),
                 vec2(
                     (screen_width() - dialog_width) / 2.0,
@@ -341,81 +759,159 @@ async fn main() {
                 vec2(dialog_width, dialog_height),
                 |ui| {
                     // Input Box
-                    ui.input_text(hash!(), "Filename", &mut filter_name);
+                    ui.input_text(hash!(), "Search", &mut filter_name);
+                    let sort_names: Vec<String> =
+                        SAVE_SORTS.iter().map(|sort| sort.to_string()).collect();
+                    let sort_names: Vec<&str> = sort_names.iter().map(|n| n.as_str()).collect();
+                    let mut sort_idx = SAVE_SORTS
+                        .iter()
+                        .position(|sort| *sort == save_sort)
+                        .unwrap_or(0);
+                    ui.combo_box(hash!(), "Sort by", &sort_names, &mut sort_idx);
+                    save_sort = SAVE_SORTS[sort_idx];
+
+                    // saves matching the search, with their name without extension
+                    let mut saves: Vec<_> = save_thumbnails
+                        .paths()
+                        .filter_map(|path| {
+                            let name = path.file_stem()?.to_str()?.to_string();
+                            let meta = save_metas.get(&path.with_extension("")).cloned();
+                            matches_query(&filter_name, &name, meta.as_ref())
+                                .then_some((path, name, meta))
+                        })
+                        .collect();
+                    if save_sort == SaveSort::Date {
+                        // saves from before sidecars existed sort by when the .slc was written
+                        saves.sort_by_cached_key(|(path, _, meta)| {
+                            std::cmp::Reverse(meta.as_ref().map_or_else(
+                                || file_modified(&path.with_extension("slc")),
+                                |meta| meta.modified,
+                            ))
+                        });
+                    }
 
-                    // filter through the files and list them so that we can click on one
-                    let mut save_dir = get_save_dir();
-                    save_dir.push(&filter_name);
+                    let label_height = 18.0;
+                    let top = 60.0;
                     let mut x: f32 = 0.;
                     let mut y: f32 = 0.;
 
-                    // iterate through the save dir, by index, so we can get the image by index
-                    if let Ok(dir) = std::fs::read_dir(save_dir) {
-                        for entry in dir {
-                            if let Ok(entry) = entry {
-                                let path = entry.path();
-                                let file_name = path.file_name().unwrap().to_str().unwrap();
-
-                                // check if the file is a png
-                                if file_name.ends_with(".png") {
-                                    // draw the image
-                                    let img = load_img(path.to_str().unwrap());
-                                    // we need to resize the image to 1/4 of the original size
-                                    let img_width = img.width() / 8;
-                                    let img_height = img.height() / 8;
-                                    let img_texture = Texture2D::from_image(&img);
-                                    img_texture.set_filter(FilterMode::Linear);
-                                    draw_texture_ex(
-                                        &img_texture,
-                                        screen_width() / 2.0 - dialog_width / 2.0 + x as f32,
-                                        30. + screen_height() / 2.0 - dialog_height / 2.0
-                                            + y as f32,
-                                        WHITE,
-                                        DrawTextureParams {
-                                            dest_size: Some(vec2(
-                                                img_width as f32,
-                                                img_height as f32,
-                                            )),
-                                            ..Default::default()
-                                        },
-                                    );
-
-                                    // check if the image is clicked
-                                    if is_mouse_button_pressed(MouseButton::Left)
-                                        && mouse_position().0
-                                            > screen_width() / 2.0 - dialog_width / 2.0 + x as f32
-                                        && mouse_position().0
-                                            < screen_width() / 2.0 - dialog_width / 2.0
-                                                + x as f32
-                                                + img_width as f32
-                                        && mouse_position().1
-                                            > 30. + screen_height() / 2.0 - dialog_height / 2.0
-                                                + y as f32
-                                        && mouse_position().1
-                                            < 30. + screen_height() / 2.0 - dialog_height / 2.0
-                                                + y as f32
-                                                + img_height as f32
-                                    {
-                                        // load the image
-                                        let mut save_dir = get_save_dir();
-                                        save_dir.push(file_name.replace(".png", ".slc"));
-                                        world.load_from_slc(save_dir.to_str().unwrap());
-                                        game_properties.requested_load = false;
-                                        filter_name.clear();
-                                    }
-
-                                    // increment x and y
-                                    if x + img_width as f32 > dialog_width {
-                                        y += img_height as f32;
-                                        x = 0.;
-                                    } else {
-                                        x += img_width as f32;
-                                    }
-                                }
+                    // metadata of the save under the mouse, shown under the thumbnails
+                    let mut hovered_meta = None;
+
+                    // thumbnails come from the cache, saves still being decoded or with a broken png
+                    // get an empty tile so they can still be loaded, renamed and deleted
+                    for (path, name, meta) in saves {
+                        let thumb = save_thumbnails.get(path);
+                        // saves are shown at 1/8 of the world size
+                        let (img_width, img_height) = match (thumb, meta.as_ref()) {
+                            (Some(thumb), _) => (thumb.width / 8.0, thumb.height / 8.0),
+                            (None, Some(meta)) => {
+                                (meta.width as f32 / 8.0, meta.height as f32 / 8.0)
                             }
+                            (None, None) => (w as f32 / 8.0, h as f32 / 8.0),
+                        };
+                        let thumb_x = screen_width() / 2.0 - dialog_width / 2.0 + x;
+                        let thumb_y = top + screen_height() / 2.0 - dialog_height / 2.0 + y;
+                        if let Some(thumb) = thumb {
+                            draw_texture_ex(
+                                &thumb.texture,
+                                thumb_x,
+                                thumb_y,
+                                WHITE,
+                                DrawTextureParams {
+                                    dest_size: Some(vec2(img_width, img_height)),
+                                    ..Default::default()
+                                },
+                            );
+                        } else {
+                            draw_placeholder(
+                                thumb_x,
+                                thumb_y,
+                                img_width,
+                                img_height,
+                                save_thumbnails.failed(path),
+                            );
+                        }
+                        // name under the thumbnail, cut to its width
+                        let max_chars = (img_width / 8.0) as usize;
+                        let label: String = name.chars().take(max_chars.max(1)).collect();
+                        ui.label(Some(vec2(x, top + y + img_height)), &label);
+
+                        let (mouse_x, mouse_y) = mouse_position();
+                        let hovered = mouse_x > thumb_x
+                            && mouse_x < thumb_x + img_width
+                            && mouse_y > thumb_y
+                            && mouse_y < thumb_y + img_height + label_height;
+                        let edge_mode =
+                            meta.as_ref().map_or(EdgeMode::Solid, |meta| meta.edge_mode);
+                        if hovered {
+                            hovered_meta = meta;
+                        }
+                        // right click picks the save for rename, duplicate and delete
+                        if hovered && is_mouse_button_pressed(MouseButton::Right) {
+                            save_actions.select(path.with_extension(""));
+                        }
+                        if save_actions.target.as_deref() == Some(path.with_extension("").as_path())
+                        {
+                            draw_rectangle_lines(
+                                thumb_x, thumb_y, img_width, img_height, 2.0, WHITE,
+                            );
+                        }
+
+                        // check if the image is clicked
+                        if hovered && is_mouse_button_pressed(MouseButton::Left) {
+                            // load the image
+                            let slc = path.with_extension("slc");
+                            if !slc.is_file() {
+                                toasts.push(format!("{}: save file is missing", slc.display()));
+                            } else if let Some(slc) = toasts.report(path_str(&slc)) {
+                                world.load_from_slc(slc);
+                                forget_world_edits(&mut history, &mut world_info.properties);
+                                game_properties.edge_mode = edge_mode;
+                                game_properties.requested_load = false;
+                                filter_name.clear();
+                            }
+                        }
+
+                        // increment x and y
+                        if x + img_width * 2.0 > dialog_width {
+                            y += img_height + label_height;
+                            x = 0.;
+                        } else {
+                            x += img_width;
                         }
                     }
 
+                    if let Some(meta) = hovered_meta {
+                        let lines = [
+                            format!("{} by {}", meta.title, meta.author),
+                            meta.description.replace('\n', " "),
+                            format!(
+                                "{}x{}, {} particles, silica {}",
+                                meta.width, meta.height, meta.particles, meta.version
+                            ),
+                            format!(
+                                "created {}, modified {}",
+                                format_timestamp(meta.created),
+                                format_timestamp(meta.modified)
+                            ),
+                        ];
+                        let mut line_y = dialog_height - button_height - 30.0 - 20.0 * 4.0;
+                        for line in lines.iter() {
+                            ui.label(Some(vec2(10.0, line_y)), line);
+                            line_y += 20.0;
+                        }
+                    }
+
+                    let actions_position = vec2(
+                        dialog_width - 290.0,
+                        dialog_height - button_height - 30.0 - 20.0 * 4.0,
+                    );
+                    if draw_save_actions(ui, &mut save_actions, actions_position) {
+                        save_thumbnails.refresh();
+                        save_metas.clear();
+                    }
+
                     // Cancel Button
                     if widgets::Button::new("Cancel")
                         .position(cancel_button_position)
@@ -443,7 +939,7 @@ async fn main() {
                 dialog_height - button_height - 20.0,
             ); // Adjust the position of the Cancel button
 
-            root_ui().window(
+            pinned_window(
                 hash!(),
                 vec2(
                     (screen_width() - dialog_width) / 2.0,
@@ -453,25 +949,64 @@ async fn main() {
                 |ui| {
                     // Input Box
                     ui.input_text(hash!(), "Filename", &mut chosen_name);
+                    ui.input_text(hash!(), "Title", &mut save_meta.title);
+                    ui.input_text(hash!(), "Description", &mut save_meta.description);
+                    ui.input_text(hash!(), "Author", &mut save_meta.author);
+
+                    // the first click on an existing name only asks, the second one overwrites
+                    let confirmed = overwrite_name.as_deref() == Some(chosen_name.as_str());
+                    if confirmed {
+                        ui.label(None, "A save with this name exists, it will be replaced");
+                    }
+                    let save_label = if confirmed { "Overwrite" } else { "Save" };
 
                     // Save Button
-                    if widgets::Button::new("Save")
+                    if widgets::Button::new(save_label)
                         .position(save_button_position)
                         .size(vec2(dialog_width / 2.0 - 20.0, button_height))
                         .ui(ui)
+                        && !chosen_name.is_empty()
                     {
-                        // save the file
-                        let mut save_dir = get_save_dir();
-                        save_dir.push(&chosen_name);
-                        world.save(save_dir.to_str().unwrap());
-                        let path = save_dir.to_str().unwrap();
-                        let fname = format!("{}.png", path);
-                        // concat chosen_name with .png and the save dir
-
-                        image.export_png(&fname);
-                        world.save_to_slc(path);
-                        game_properties.requested_save = false;
-                        chosen_name.clear();
+                        let save_dir = get_save_dir().map(|dir| dir.join(&chosen_name));
+                        match save_dir {
+                            Err(err) => toasts.push(err),
+                            Ok(save_dir) if !confirmed && save_exists(&save_dir) => {
+                                overwrite_name = Some(chosen_name.clone());
+                            }
+                            Ok(save_dir) => {
+                                overwrite_name = None;
+                                // overwriting a save keeps its created time
+                                let created = SaveMeta::load(&save_dir)
+                                    .map(|meta| meta.created)
+                                    .unwrap_or_else(now);
+                                let meta = SaveMeta {
+                                    title: if save_meta.title.is_empty() {
+                                        chosen_name.clone()
+                                    } else {
+                                        save_meta.title.clone()
+                                    },
+                                    width: world.width as usize,
+                                    height: world.height as usize,
+                                    particles: world.get_particle_count(),
+                                    created,
+                                    modified: now(),
+                                    version: env!("CARGO_PKG_VERSION").to_string(),
+                                    edge_mode: game_properties.edge_mode,
+                                    ..save_meta.clone()
+                                };
+                                // the dialog stays open when the save failed
+                                let saved =
+                                    toasts.report(write_save(&world, &image, &save_dir, &meta));
+                                save_thumbnails.refresh();
+                                save_metas.clear();
+                                if saved.is_some() {
+                                    save_meta.title.clear();
+                                    save_meta.description.clear();
+                                    game_properties.requested_save = false;
+                                    chosen_name.clear();
+                                }
+                            }
+                        }
                     }
 
                     // Cancel Button
@@ -481,6 +1016,7 @@ async fn main() {
                         .ui(ui)
                     {
                         game_properties.requested_save = false;
+                        overwrite_name = None;
                     }
                 },
             );
@@ -490,19 +1026,26 @@ async fn main() {
             draw_confirm_exit(game_properties);
         }
 
+        // autosaves have no sidecar, the edge mode of this session is kept
+        if game_properties.requested_restore && draw_restore_dialog(&mut game_properties) {
+            if let Some(path) = latest_autosave() {
+                if let Some(path) = toasts.report(path_str(&path)) {
+                    world.load_from_slc(path);
+                    forget_world_edits(&mut history, &mut world_info.properties);
+                }
+            }
+        }
+
         texture.update(&image);
         if can_draw {
             draw_texture_ex(
                 &texture,
-                0.,
-                0.,
+                world_view.x,
+                world_view.y,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(vec2(
-                        screen_width() - UI_OFFSET_X,
-                        screen_height() - UI_OFFSET_Y, //ADJUST BASED ON BUTTON SIZE,
-                    )),
-                    source: Some(Rect::new(0.0, 0.0, w as f32, h as f32)),
+                    dest_size: Some(world_view.size()),
+                    source: Some(world_info.camera.visible_rect(w, h)),
 
                     ..Default::default()
                 },
@@ -513,13 +1056,93 @@ async fn main() {
         draw_top_panel(&mut world_info);
         draw_bottom_panel(&mut world_info, &mut game_properties);
         if can_draw {
-            draw_tool_outline(&mut world_info);
+            draw_selection(&world_info);
+            draw_shape_preview(&world_info);
+            if world_info.properties.pasting {
+                draw_paste_preview(&clipboard, &world_info);
+            } else {
+                draw_tool_outline(&mut world_info, brush);
+            }
+            // holding the magnifier key shows a magnified inset at the mouse position
+            if hotkeys && keybindings.down(Action::Magnifier) {
+                draw_magnifier(&texture, &world_info);
+            }
+
+            match draw_clipboard_panel(&clipboard, &world_info).or(clipboard_action) {
+                Some(ClipboardAction::SaveStamp) => game_properties.requested_stamp_save = true,
+                Some(ClipboardAction::OpenStamps) => game_properties.requested_stamp_load = true,
+                Some(action) => handle_clipboard_action(
+                    action,
+                    &mut clipboard,
+                    &mut world,
+                    &mut history,
+                    &mut world_info.properties,
+                ),
+                None => (),
+            }
         }
 
+        if game_properties.requested_stamp_save
+            && draw_stamp_save_dialog(&mut stamp_name, &mut game_properties)
+        {
+            if let Some((start, end)) = world_info.properties.selection {
+                if let Some(region) = Region::capture(&world, start, end) {
+                    toasts.report(save_stamp(&region, &stamp_name));
+                    stamp_thumbnails.refresh();
+                }
+            }
+            stamp_name.clear();
+        }
+
+        if game_properties.requested_stamp_load {
+            // picking a stamp puts it on the clipboard and starts placing it
+            if let Some(slc) = draw_stamp_browser(&mut stamp_thumbnails, &mut game_properties) {
+                // a png left behind without its .slc would load as an empty world
+                if !slc.is_file() {
+                    toasts.push(format!("{}: stamp file is missing", slc.display()));
+                } else if let Some(region) = toasts.report(path_str(&slc)).and_then(load_stamp) {
+                    clipboard.set(region);
+                    world_info.properties.pasting = true;
+                }
+            }
+        }
+
+        if can_draw {
+            draw_tool_options(&element_manager, &mut world_info, &mut temperature_inputs);
+            if game_properties.render_mode == RenderMode::Heat {
+                draw_heat_legend(&mut world_info);
+            }
+        }
         draw_group_sidebar(&element_manager, &mut world_info);
         draw_element_list(&element_manager, &mut world_info);
         let end = get_time();
 
+        // write settings once they settle instead of on every frame of a drag, scroll or resize
+        let mut current_settings =
+            Settings::from_properties(&world_info.properties, saved_settings);
+        // the render mode is switched on game_properties, not world_info
+        current_settings.render_mode = game_properties.render_mode;
+        current_settings.keybindings = keybindings;
+        // the fullscreen size isn't remembered, the window comes back at its windowed size
+        if !fullscreen {
+            current_settings.window_width = screen_width() as i32;
+            current_settings.window_height = screen_height() as i32;
+        }
+        if current_settings == saved_settings {
+            unsaved_settings = None;
+        } else if unsaved_settings.map(|(unsaved, _)| unsaved) != Some(current_settings) {
+            unsaved_settings = Some((current_settings, get_time()));
+        } else if unsaved_settings
+            .is_some_and(|(_, changed_at)| get_time() - changed_at >= SETTINGS_SAVE_DELAY)
+            && !game_properties.left_mouse_down
+        {
+            // a failed write isn't retried every frame, the next change tries again
+            toasts.report(current_settings.save());
+            saved_settings = current_settings;
+            unsaved_settings = None;
+        }
+        toasts.draw();
+
         // every half a second, update the fps counter
         if world.generation % 10 == 0 {
             world_info.fps = get_fps() as f32;
@@ -528,6 +1151,14 @@ async fn main() {
     }
 }
 
+// undo steps and selections point at cells of the world that was just replaced
+fn forget_world_edits(history: &mut History, props: &mut GameProperties) {
+    *history = History::new(history.budget);
+    props.selection = None;
+    props.shape_start = None;
+    props.pasting = false;
+}
+
 fn register_element_groups(manager: &ElementManager) {
     manager.register_group("PWDR", vec![Variant::Sand, Variant::Salt]);
     manager.register_group("FLUID", vec![Variant::Water, Variant::SaltWater]);
@@ -549,36 +1180,16 @@ fn register_element_groups(manager: &ElementManager) {
     manager.register_group("Life", vec![Variant::GOL])
 }
 
-pub fn temp_to_color(temperature: f32) -> (u8, u8, u8) {
-    // red is hottest, blue is coldest. Use gradient
-
-    let mut color = (0, 0, 0);
-    if temperature < 0.0 {
-        color = (0, 0, 255);
-    } else if temperature < 100.0 {
-        // blue to green
-        let blue = 255 - (temperature / 100.0 * 255.0) as u8;
-        let green = (temperature / 100.0 * 255.0) as u8;
-        color = (0, green, blue);
-    } else if temperature < 200.0 {
-        // green to yellow
-        let green = 255 - ((temperature - 100.0) / 100.0 * 255.0) as u8;
-        let red = ((temperature - 100.0) / 100.0 * 255.0) as u8;
-        color = (red, green, 0);
-    } else if temperature < 300.0 {
-        // yellow to red
-        let red = 255 - ((temperature - 200.0) / 100.0 * 255.0) as u8;
-        let green = ((temperature - 200.0) / 100.0 * 255.0) as u8;
-        color = (red, green, 0);
+// pressure at which the pressure map saturates, either way
+const PRESSURE_RANGE: f32 = 100.0;
+
+pub fn pressure_to_color(pressure: f32) -> (u8, u8, u8) {
+    // diverging map, zero is black so small pressure waves stand out
+    let t = (pressure / PRESSURE_RANGE).clamp(-1.0, 1.0);
+    let level = (t.abs() * 255.0) as u8;
+    if t < 0.0 {
+        (0, level / 2, level)
     } else {
-        color = (255, 0, 0);
+        (level, level / 2, 0)
     }
-
-    color
-}
-
-pub fn load_img(path: &str) -> Image {
-    let vec = std::fs::read(path).unwrap();
-    // we need to resize the image to 1/4 of the original size
-    Image::from_file_with_format(&vec, None).unwrap()
 }
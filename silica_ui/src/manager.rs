@@ -2,27 +2,112 @@ use std::fmt::Display;
 
 use silica_engine::variant::{Particle, Variant};
 
+use crate::brush::BrushShape;
+use crate::camera::Camera;
+use crate::colormap::HeatScale;
+use crate::new_world::EdgeMode;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 
 pub enum RenderMode {
     Normal,
     Heat,
+    Pressure,
 }
 #[derive(Clone, Copy, Debug)]
 pub struct GameProperties {
     pub tool_radius: f32,
     pub tool_type: Tool,
+    pub brush_shape: BrushShape,
+    // which png in the brushes dir the custom brush uses, wraps around
+    pub custom_brush_idx: usize,
+    // percentage of brush cells filled per stamp, 100 paints solid
+    pub spray_density: f32,
+    pub paint_filter: PaintFilter,
+    // right click erasing has its own filter, e.g. only erase water
+    pub erase_filter: PaintFilter,
+    // the tool options cover the top left of the world, so they are opened from the bottom panel
+    pub show_tool_options: bool,
+
+    pub heat_settings: ToolSettings,
+    pub cool_settings: ToolSettings,
+    pub pressure_settings: ToolSettings,
+    // exact temperature the TEMP tool sets cells to
+    pub set_temperature: f32,
+    // painted elements spawn at paint_temperature instead of ambient
+    pub paint_at_temperature: bool,
+    pub paint_temperature: f32,
+    // seconds between autosaves, 0 turns them off
+    pub autosave_interval: f32,
     pub hovering_over: Particle,
     pub hovering_temperature: f32,
     pub selected_group_idx: usize,
 
     pub left_mouse_down: bool,
     pub right_mouse_down: bool,
+    // world position of the mouse on the previous frame while a button is held
+    pub last_mouse_world: Option<(i32, i32)>,
+    // the left button went down over a ui window and hasn't been released yet
+    pub dragging_ui: bool,
+    // world point held under the cursor while middle dragging
+    pub pan_anchor: Option<(f32, f32)>,
+    // the middle drag moved off its cell, so releasing it doesn't pick
+    pub panned: bool,
+
+    pub shape: Shape,
+    // world position where the current shape drag started
+    pub shape_start: Option<(i32, i32)>,
+
+    // corners of the selected region in world coords
+    pub selection: Option<((i32, i32), (i32, i32))>,
+    pub selecting: bool,
+    // the clipboard follows the cursor and is placed on click
+    pub pasting: bool,
 
     pub render_mode: RenderMode,
+    pub heat_scale: HeatScale,
     pub requested_exit: bool,
     pub requested_save: bool,
     pub requested_load: bool,
+    pub requested_stamp_save: bool,
+    pub requested_stamp_load: bool,
+    pub requested_new: bool,
+    // the last session crashed and an autosave can be restored
+    pub requested_restore: bool,
+    pub requested_keybindings: bool,
+    pub edge_mode: EdgeMode,
+}
+
+impl GameProperties {
+    pub fn tool_settings_mut(&mut self, property: Property) -> Option<&mut ToolSettings> {
+        match property {
+            Property::Temperature => Some(&mut self.heat_settings),
+            Property::COOL => Some(&mut self.cool_settings),
+            Property::Pressure => Some(&mut self.pressure_settings),
+            Property::SetTemp | Property::DelWall => None,
+        }
+    }
+
+    // temperature newly painted elements start at, None leaves them at ambient
+    pub fn spawn_temperature(&self) -> Option<f32> {
+        if self.paint_at_temperature {
+            Some(self.paint_temperature)
+        } else {
+            None
+        }
+    }
+
+    // while a dialog is up the world is paused and doesn't take input
+    pub fn is_dialog_open(&self) -> bool {
+        self.requested_exit
+            || self.requested_save
+            || self.requested_load
+            || self.requested_stamp_save
+            || self.requested_stamp_load
+            || self.requested_new
+            || self.requested_restore
+            || self.requested_keybindings
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -30,13 +115,113 @@ pub enum Property {
     Temperature,
     COOL,
     Pressure,
+    SetTemp,
     DelWall,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Falloff {
+    Gaussian,
+    Linear,
+    Flat,
+}
+
+impl Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderMode::Normal => write!(f, "normal"),
+            RenderMode::Heat => write!(f, "heat"),
+            RenderMode::Pressure => write!(f, "pressure"),
+        }
+    }
+}
+
+impl Display for Falloff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Falloff::Gaussian => write!(f, "gaussian"),
+            Falloff::Linear => write!(f, "linear"),
+            Falloff::Flat => write!(f, "flat"),
+        }
+    }
+}
+
+// how strongly HEAT, COOL and PRSR act on each cell of the brush
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToolSettings {
+    pub strength: f32,
+    pub falloff: Falloff,
+    // sigma for gaussian, distance to zero for linear
+    pub spread: f32,
+    // cells further than this are left alone, 0 uses the whole brush
+    pub clip_radius: f32,
+}
+
+impl ToolSettings {
+    pub const HEAT: ToolSettings = ToolSettings {
+        strength: 40.0,
+        falloff: Falloff::Gaussian,
+        spread: 10.0,
+        clip_radius: 0.0,
+    };
+    pub const PRESSURE: ToolSettings = ToolSettings {
+        strength: 100.0,
+        falloff: Falloff::Gaussian,
+        spread: 0.5,
+        clip_radius: 0.0,
+    };
+
+    // amount applied at an offset from the brush center, None when clipped
+    pub fn intensity(&self, dx: i32, dy: i32) -> Option<f32> {
+        let distance_squared = (dx * dx + dy * dy) as f32;
+        if self.clip_radius > 0.0 && distance_squared > self.clip_radius * self.clip_radius {
+            return None;
+        }
+        let factor = match self.falloff {
+            Falloff::Gaussian => (-distance_squared / (2.0 * self.spread.powi(2))).exp(),
+            Falloff::Linear => (1.0 - distance_squared.sqrt() / self.spread).max(0.0),
+            Falloff::Flat => 1.0,
+        };
+        Some(self.strength * factor)
+    }
+}
+
+// decides which cells a brush is allowed to overwrite
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaintFilter {
+    All,
+    OnlyEmpty,
+    ReplaceOnly(Variant),
+    ProtectWalls,
+}
+
+impl PaintFilter {
+    pub fn allows(&self, current: Variant) -> bool {
+        match self {
+            PaintFilter::All => true,
+            PaintFilter::OnlyEmpty => current == Variant::Empty,
+            PaintFilter::ReplaceOnly(source) => current == *source,
+            PaintFilter::ProtectWalls => current != Variant::Wall,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Freehand,
+    Line,
+    Rect,
+    FilledRect,
+    Ellipse,
+    Fill,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Tool {
     ElementTool(Variant),
     PropertyTool(Property),
+    SelectTool,
+    PickerTool,
 }
 
 impl Display for Property {
@@ -45,16 +230,31 @@ impl Display for Property {
             Property::Temperature => write!(f, "HEAT"),
             Property::COOL => write!(f, "COOL"),
             Property::Pressure => write!(f, "PRSR"),
+            Property::SetTemp => write!(f, "TEMP"),
             Property::DelWall => write!(f, "DELW"),
         }
     }
 }
 
+impl Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shape::Freehand => write!(f, "FREE"),
+            Shape::Line => write!(f, "LINE"),
+            Shape::Rect => write!(f, "RECT"),
+            Shape::FilledRect => write!(f, "FRCT"),
+            Shape::Ellipse => write!(f, "ELPS"),
+            Shape::Fill => write!(f, "FILL"),
+        }
+    }
+}
+
 impl Tool {
     pub fn get_property(&self) -> Option<Property> {
         match self {
             Tool::ElementTool(_variant) => None,
             Tool::PropertyTool(property) => Some(*property),
+            Tool::SelectTool | Tool::PickerTool => None,
         }
     }
 
@@ -62,6 +262,7 @@ impl Tool {
         match self {
             Tool::ElementTool(variant) => Some(*variant),
             Tool::PropertyTool(_property) => None,
+            Tool::SelectTool | Tool::PickerTool => None,
         }
     }
 }
@@ -72,4 +273,5 @@ pub struct WorldInfo {
     pub world_width: usize,
     pub world_height: usize,
     pub parts: usize,
+    pub camera: Camera,
 }
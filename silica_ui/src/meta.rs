@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::data::{write_file, DataError};
+use crate::new_world::EdgeMode;
+use crate::settings::{parse_values, read_value};
+
+// what we know about a save besides its cells, kept in <name>.meta next to <name>.slc
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SaveMeta {
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub width: usize,
+    pub height: usize,
+    pub particles: usize,
+    // seconds since the unix epoch
+    pub created: u64,
+    pub modified: u64,
+    // version of silica that wrote the save
+    pub version: String,
+    // saves without it are from before edge modes and have solid edges
+    pub edge_mode: EdgeMode,
+}
+
+impl SaveMeta {
+    // sidecar for a save, save is the path without any extension
+    pub fn path_for(save: &Path) -> PathBuf {
+        let mut path = save.as_os_str().to_owned();
+        path.push(".meta");
+        PathBuf::from(path)
+    }
+
+    pub fn load(save: &Path) -> Option<SaveMeta> {
+        let contents = std::fs::read_to_string(SaveMeta::path_for(save)).ok()?;
+        let values = parse_values(&contents);
+        let text = |key: &str| unescape(&read_value(&values, key, String::new()));
+        Some(SaveMeta {
+            title: text("title"),
+            description: text("description"),
+            author: text("author"),
+            width: read_value(&values, "width", 0),
+            height: read_value(&values, "height", 0),
+            particles: read_value(&values, "particles", 0),
+            created: read_value(&values, "created", 0),
+            modified: read_value(&values, "modified", 0),
+            version: text("version"),
+            edge_mode: read_value(&values, "edge_mode", EdgeMode::Solid),
+        })
+    }
+
+    pub fn save(&self, save: &Path) -> Result<(), DataError> {
+        write_file(&SaveMeta::path_for(save), self.to_string())
+    }
+}
+
+impl Display for SaveMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "title={}", escape(&self.title))?;
+        writeln!(f, "description={}", escape(&self.description))?;
+        writeln!(f, "author={}", escape(&self.author))?;
+        writeln!(f, "width={}", self.width)?;
+        writeln!(f, "height={}", self.height)?;
+        writeln!(f, "particles={}", self.particles)?;
+        writeln!(f, "created={}", self.created)?;
+        writeln!(f, "modified={}", self.modified)?;
+        writeln!(f, "version={}", escape(&self.version))?;
+        writeln!(f, "edge_mode={}", self.edge_mode)
+    }
+}
+
+// sidecars read by the load dialog, each is read once until the cache is cleared
+#[derive(Default)]
+pub struct MetaCache {
+    metas: HashMap<PathBuf, Option<SaveMeta>>,
+}
+
+impl MetaCache {
+    pub fn get(&mut self, save: &Path) -> Option<&SaveMeta> {
+        self.metas
+            .entry(save.to_path_buf())
+            .or_insert_with(|| SaveMeta::load(save))
+            .as_ref()
+    }
+
+    // call when saves may have changed on disk
+    pub fn clear(&mut self) {
+        self.metas.clear();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SaveSort {
+    Name,
+    // most recently modified first
+    Date,
+}
+
+pub const SAVE_SORTS: [SaveSort; 2] = [SaveSort::Name, SaveSort::Date];
+
+impl Display for SaveSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveSort::Name => write!(f, "name"),
+            SaveSort::Date => write!(f, "date"),
+        }
+    }
+}
+
+// case insensitive, a save matches when the query is a substring of its name, title, description
+// or author, or when the query's letters appear in order in its name or title
+pub fn matches_query(query: &str, name: &str, meta: Option<&SaveMeta>) -> bool {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return true;
+    }
+    let mut fields = vec![name.to_lowercase()];
+    if let Some(meta) = meta {
+        fields.push(meta.title.to_lowercase());
+        fields.push(meta.description.to_lowercase());
+        fields.push(meta.author.to_lowercase());
+    }
+    fields.iter().any(|field| field.contains(&query))
+        || fields[..fields.len().min(2)]
+            .iter()
+            .any(|field| is_subsequence(&query, field))
+}
+
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut text = text.chars();
+    query.chars().all(|q| text.any(|c| c == q))
+}
+
+// values live on one line, so newlines and backslashes are escaped
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// last write time of a file in seconds, 0 when it can't be read
+pub fn file_modified(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+// "YYYY-MM-DD HH:MM" in UTC, there is no date crate in the tree
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let minutes = secs % 86400 / 60;
+    // civil from days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+// who gets written as the author of new saves
+pub fn default_author() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_round_trips() {
+        for text in [
+            "plain",
+            "two\nlines",
+            "back\\slash",
+            "\\n literally",
+            "trailing\\",
+        ] {
+            assert_eq!(unescape(&escape(text)), text);
+        }
+        assert_eq!(escape("a\nb"), "a\\nb");
+    }
+
+    #[test]
+    fn unescape_keeps_a_lone_backslash() {
+        assert_eq!(unescape("end\\"), "end\\");
+        assert_eq!(unescape("\\x"), "x");
+    }
+
+    #[test]
+    fn format_timestamp_is_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(951_831_900), "2000-02-29 13:45");
+        assert_eq!(format_timestamp(1_735_689_540), "2024-12-31 23:59");
+    }
+
+    #[test]
+    fn sidecar_round_trips() {
+        let meta = SaveMeta {
+            title: "  spaced title ".to_string(),
+            description: "first line\nsecond = line".to_string(),
+            author: "someone".to_string(),
+            width: 611,
+            height: 383,
+            particles: 1234,
+            created: 1,
+            modified: 2,
+            version: "0.1.0".to_string(),
+            edge_mode: EdgeMode::Void,
+        };
+        let mut base = std::env::temp_dir();
+        base.push(format!("silica-meta-test-{}", std::process::id()));
+        meta.save(&base).unwrap();
+        let loaded = SaveMeta::load(&base);
+        let _ = std::fs::remove_file(SaveMeta::path_for(&base));
+        assert_eq!(loaded, Some(meta));
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert!(matches_query("", "anything", None));
+        assert!(matches_query("   ", "anything", None));
+    }
+
+    #[test]
+    fn query_matches_substrings_of_any_field() {
+        let meta = SaveMeta {
+            title: "Volcano".to_string(),
+            description: "lava flowing into the sea".to_string(),
+            author: "Ada".to_string(),
+            ..Default::default()
+        };
+        assert!(matches_query("CAN", "volcano-2", None));
+        assert!(matches_query("into the", "save", Some(&meta)));
+        assert!(matches_query("ada", "save", Some(&meta)));
+        assert!(!matches_query("glacier", "save", Some(&meta)));
+    }
+
+    #[test]
+    fn letters_in_order_match_name_and_title_only() {
+        let meta = SaveMeta {
+            title: "Salt Water Tank".to_string(),
+            description: "xyz".to_string(),
+            ..Default::default()
+        };
+        assert!(matches_query("swt", "save", Some(&meta)));
+        assert!(matches_query("vlc", "volcano", None));
+        assert!(!matches_query("xz", "save", Some(&meta)));
+        assert!(!matches_query("tws", "save", Some(&meta)));
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use macroquad::prelude::*;
+use macroquad::ui::{hash, widgets};
+
+use crate::manager::GameProperties;
+use crate::utils::pinned_window;
+
+pub const MIN_WORLD_SIZE: usize = 16;
+pub const MAX_WORLD_SIZE: usize = 4096;
+
+// what happens to particles that reach the edge of the world
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EdgeMode {
+    // the edge stops them like a wall
+    #[default]
+    Solid,
+    // they fall out of the world and are removed
+    Void,
+}
+
+impl Display for EdgeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdgeMode::Solid => write!(f, "solid"),
+            EdgeMode::Void => write!(f, "void"),
+        }
+    }
+}
+
+impl FromStr for EdgeMode {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        EDGE_MODES
+            .into_iter()
+            .find(|mode| mode.to_string() == text)
+            .ok_or(())
+    }
+}
+
+const EDGE_MODES: [EdgeMode; 2] = [EdgeMode::Solid, EdgeMode::Void];
+
+// what the new world dialog is filled in with, the sizes are kept as typed
+pub struct NewWorldOptions {
+    pub width: String,
+    pub height: String,
+    pub edge_mode: EdgeMode,
+    pub border: bool,
+}
+
+impl NewWorldOptions {
+    pub fn new(width: usize, height: usize) -> NewWorldOptions {
+        NewWorldOptions {
+            width: width.to_string(),
+            height: height.to_string(),
+            edge_mode: EdgeMode::Solid,
+            border: true,
+        }
+    }
+
+    // None while either size isn't a number in the allowed range
+    pub fn size(&self) -> Option<(usize, usize)> {
+        let parse = |text: &str| {
+            text.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|size| (MIN_WORLD_SIZE..=MAX_WORLD_SIZE).contains(size))
+        };
+        Some((parse(&self.width)?, parse(&self.height)?))
+    }
+}
+
+// returns true when create was clicked with a valid size
+pub fn draw_new_world_dialog(options: &mut NewWorldOptions, props: &mut GameProperties) -> bool {
+    let dialog_width = screen_width() * 0.4;
+    let dialog_height = screen_height() * 0.4;
+    let button_height = 50.0;
+    let mut create = false;
+
+    pinned_window(
+        hash!(),
+        vec2(
+            (screen_width() - dialog_width) / 2.0,
+            (screen_height() - dialog_height) / 2.0,
+        ),
+        vec2(dialog_width, dialog_height),
+        |ui| {
+            ui.input_text(hash!(), "Width", &mut options.width);
+            ui.input_text(hash!(), "Height", &mut options.height);
+            let names: Vec<String> = EDGE_MODES.iter().map(|mode| mode.to_string()).collect();
+            let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+            let mut mode_idx = EDGE_MODES
+                .iter()
+                .position(|mode| *mode == options.edge_mode)
+                .unwrap_or(0);
+            ui.combo_box(hash!(), "Edges", &names, &mut mode_idx);
+            options.edge_mode = EDGE_MODES[mode_idx];
+            ui.checkbox(hash!(), "Wall border", &mut options.border);
+            if options.size().is_none() {
+                ui.label(
+                    None,
+                    &format!(
+                        "Sizes must be between {} and {}",
+                        MIN_WORLD_SIZE, MAX_WORLD_SIZE
+                    ),
+                );
+            }
+
+            if widgets::Button::new("Create")
+                .position(vec2(10.0, dialog_height - button_height - 20.0))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+                && options.size().is_some()
+            {
+                create = true;
+                props.requested_new = false;
+            }
+
+            if widgets::Button::new("Cancel")
+                .position(vec2(
+                    dialog_width / 2.0,
+                    dialog_height - button_height - 20.0,
+                ))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                props.requested_new = false;
+            }
+        },
+    );
+    create
+}
@@ -0,0 +1,311 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use macroquad::prelude::*;
+use macroquad::ui::{hash, widgets, Ui};
+use silica_engine::world::World;
+
+use crate::data::{create_save_dir, export_png, path_str, DataError};
+use crate::meta::{now, SaveMeta};
+
+// a save is every file sharing its base name, world.save writes the bare name
+// save_to_slc adds .slc, the thumbnail is .png and the metadata sidecar is .meta
+const SAVE_EXTENSIONS: [&str; 4] = ["", ".slc", ".png", ".meta"];
+
+fn with_suffix(base: &Path, suffix: &str) -> PathBuf {
+    let mut path: OsString = base.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+// files of the save at base that are actually on disk
+fn save_files(base: &Path) -> Vec<(PathBuf, &'static str)> {
+    SAVE_EXTENSIONS
+        .iter()
+        .map(|ext| (with_suffix(base, ext), *ext))
+        .filter(|(path, _)| path.is_file())
+        .collect()
+}
+
+// writes every file of the save at base, the save dir is created again if it was removed
+pub fn write_save(
+    world: &World,
+    image: &Image,
+    base: &Path,
+    meta: &SaveMeta,
+) -> Result<(), DataError> {
+    create_save_dir()?;
+    let path = path_str(base)?;
+    world.save(path);
+    export_png(image, &with_suffix(base, ".png"))?;
+    world.save_to_slc(path);
+    meta.save(base)
+}
+
+pub fn save_exists(base: &Path) -> bool {
+    !save_files(base).is_empty()
+}
+
+pub fn rename_save(base: &Path, new_name: &str) -> io::Result<PathBuf> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains(['/', '\\']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a valid save name",
+        ));
+    }
+    let new_base = base.with_file_name(new_name);
+    if save_exists(&new_base) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("a save called {} already exists", new_name),
+        ));
+    }
+    let mut moved = vec![];
+    for (path, ext) in save_files(base) {
+        let new_path = with_suffix(&new_base, ext);
+        if let Err(err) = std::fs::rename(&path, &new_path) {
+            // move back what was already renamed so the save isn't split across two names
+            for (old_path, new_path) in moved.iter().rev() {
+                let _ = std::fs::rename(new_path, old_path);
+            }
+            return Err(err);
+        }
+        moved.push((path, new_path));
+    }
+    Ok(new_base)
+}
+
+// copies every file of the save to the first free "<name> copy N"
+// the copy's sidecar is new, created now and titled as a copy
+pub fn duplicate_save(base: &Path) -> io::Result<PathBuf> {
+    let name = base
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("save");
+    let mut copy = 1;
+    let new_base = loop {
+        let candidate = if copy == 1 {
+            base.with_file_name(format!("{} copy", name))
+        } else {
+            base.with_file_name(format!("{} copy {}", name, copy))
+        };
+        if !save_exists(&candidate) {
+            break candidate;
+        }
+        copy += 1;
+    };
+    let meta = SaveMeta::load(base).map(|meta| SaveMeta {
+        title: if meta.title.is_empty() {
+            meta.title
+        } else {
+            format!("{} (copy)", meta.title)
+        },
+        created: now(),
+        modified: now(),
+        ..meta
+    });
+    let copied = save_files(base).into_iter().try_for_each(|(path, ext)| {
+        let new_path = with_suffix(&new_base, ext);
+        match (&meta, ext) {
+            (Some(meta), ".meta") => std::fs::write(new_path, meta.to_string()),
+            _ => std::fs::copy(path, new_path).map(|_| ()),
+        }
+    });
+    if let Err(err) = copied {
+        // no half copied save is left behind
+        let _ = delete_save(&new_base);
+        return Err(err);
+    }
+    Ok(new_base)
+}
+
+pub fn delete_save(base: &Path) -> io::Result<()> {
+    for (path, _) in save_files(base) {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+// the save picked with a right click in the load dialog and what is typed for it
+#[derive(Default)]
+pub struct SaveActions {
+    pub target: Option<PathBuf>,
+    pub new_name: String,
+    // delete needs a second click
+    pub confirm_delete: bool,
+    pub error: Option<String>,
+}
+
+impl SaveActions {
+    pub fn select(&mut self, base: PathBuf) {
+        self.new_name = base
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        self.target = Some(base);
+        self.confirm_delete = false;
+        self.error = None;
+    }
+}
+
+// rename, duplicate and delete buttons for the selected save, returns true when files changed
+pub fn draw_save_actions(ui: &mut Ui, actions: &mut SaveActions, position: Vec2) -> bool {
+    let Some(target) = actions.target.clone() else {
+        return false;
+    };
+    let button_width = 90.0;
+    let button_height = 30.0;
+    let mut changed = false;
+
+    ui.label(
+        Some(position),
+        &format!(
+            "Selected: {}",
+            target.file_name().and_then(|n| n.to_str()).unwrap_or("")
+        ),
+    );
+    widgets::InputText::new(hash!())
+        .position(vec2(position.x, position.y + 20.0))
+        .size(vec2(button_width * 3.0, 22.0))
+        .ui(ui, &mut actions.new_name);
+
+    let y = position.y + 48.0;
+    let rename = widgets::Button::new("RENAME")
+        .position(vec2(position.x, y))
+        .size(vec2(button_width, button_height))
+        .ui(ui);
+    let duplicate = widgets::Button::new("DUPLICATE")
+        .position(vec2(position.x + button_width, y))
+        .size(vec2(button_width, button_height))
+        .ui(ui);
+    let delete_label = if actions.confirm_delete {
+        "CONFIRM"
+    } else {
+        "DELETE"
+    };
+    let delete = widgets::Button::new(delete_label)
+        .position(vec2(position.x + button_width * 2.0, y))
+        .size(vec2(button_width, button_height))
+        .ui(ui);
+
+    // Some(new target) after a rename or duplicate, None once deleted
+    let result = if rename {
+        Some(rename_save(&target, &actions.new_name).map(Some))
+    } else if duplicate {
+        Some(duplicate_save(&target).map(Some))
+    } else if delete && actions.confirm_delete {
+        Some(delete_save(&target).map(|_| None))
+    } else {
+        if delete {
+            actions.confirm_delete = true;
+        }
+        None
+    };
+
+    match result {
+        Some(Ok(new_target)) => {
+            match new_target {
+                Some(base) => actions.select(base),
+                None => *actions = SaveActions::default(),
+            }
+            changed = true;
+        }
+        Some(Err(err)) => actions.error = Some(err.to_string()),
+        None => (),
+    }
+    if let Some(error) = &actions.error {
+        ui.label(Some(vec2(position.x, y + button_height + 5.0)), error);
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an empty dir of its own under the system temp dir
+    fn test_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("silica-saves-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_files(base: &Path, extensions: &[&str]) {
+        for ext in extensions {
+            std::fs::write(with_suffix(base, ext), ext).unwrap();
+        }
+    }
+
+    #[test]
+    fn rename_moves_every_file() {
+        let dir = test_dir("rename");
+        let base = dir.join("old");
+        write_files(&base, &["", ".slc", ".png"]);
+
+        let new_base = rename_save(&base, " new ").unwrap();
+
+        assert_eq!(new_base, dir.join("new"));
+        assert!(!save_exists(&base));
+        assert_eq!(save_files(&new_base).len(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_rename_moves_files_back() {
+        let dir = test_dir("rollback");
+        let base = dir.join("old");
+        write_files(&base, &["", ".slc", ".png"]);
+        // a dir where the png would go lets the first two renames through, then fails
+        std::fs::create_dir(dir.join("new.png")).unwrap();
+
+        assert!(rename_save(&base, "new").is_err());
+
+        assert_eq!(save_files(&base).len(), 3);
+        assert!(!save_exists(&dir.join("new")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rename_refuses_taken_and_invalid_names() {
+        let dir = test_dir("taken");
+        let base = dir.join("old");
+        write_files(&base, &[".slc"]);
+        write_files(&dir.join("other"), &[".slc"]);
+
+        assert!(rename_save(&base, "other").is_err());
+        assert!(rename_save(&base, "  ").is_err());
+        assert!(rename_save(&base, "a/b").is_err());
+        assert!(save_exists(&base));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn duplicate_takes_the_first_free_copy_name() {
+        let dir = test_dir("duplicate");
+        let base = dir.join("world");
+        write_files(&base, &["", ".slc"]);
+        let meta = SaveMeta {
+            title: "title".to_string(),
+            created: 1,
+            modified: 1,
+            ..Default::default()
+        };
+        meta.save(&base).unwrap();
+
+        let first = duplicate_save(&base).unwrap();
+        let second = duplicate_save(&base).unwrap();
+
+        assert_eq!(first, dir.join("world copy"));
+        assert_eq!(second, dir.join("world copy 2"));
+        assert_eq!(save_files(&second).len(), 3);
+        let copied = SaveMeta::load(&first).unwrap();
+        assert_eq!(copied.title, "title (copy)");
+        assert!(copied.created > 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::autosave::DEFAULT_AUTOSAVE_INTERVAL;
+use crate::data::{get_settings_path, write_file, DataError};
+use crate::history::DEFAULT_HISTORY_BUDGET;
+use crate::keybinds::KeyBindings;
+use crate::manager::{Falloff, GameProperties, RenderMode, Tool, ToolSettings};
+use crate::TOOLS;
+use silica_engine::group::ElementManager;
+use silica_engine::variant::Variant;
+
+pub const DEFAULT_WINDOW_WIDTH: i32 = 1390;
+pub const DEFAULT_WINDOW_HEIGHT: i32 = 900;
+
+// user preferences, stored as key=value lines in the data dir
+// missing or unreadable keys fall back to the defaults
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    pub tool_radius: f32,
+    pub tool: Tool,
+    pub group_idx: usize,
+    pub render_mode: RenderMode,
+    // size of the window when it isn't fullscreen
+    pub window_width: i32,
+    pub window_height: i32,
+    pub heat: ToolSettings,
+    pub cool: ToolSettings,
+    pub pressure: ToolSettings,
+    pub autosave_interval: f32,
+    // bytes the undo history may hold
+    pub history_budget: usize,
+    pub keybindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tool_radius: 10.0,
+            tool: Tool::ElementTool(Variant::Sand),
+            group_idx: 0,
+            render_mode: RenderMode::Normal,
+            window_width: DEFAULT_WINDOW_WIDTH,
+            window_height: DEFAULT_WINDOW_HEIGHT,
+            heat: ToolSettings::HEAT,
+            cool: ToolSettings::HEAT,
+            pressure: ToolSettings::PRESSURE,
+            autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+            history_budget: DEFAULT_HISTORY_BUDGET,
+            keybindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    // elements are stored by name, the manager resolves them again
+    pub fn load(manager: &ElementManager) -> Settings {
+        let values = read_settings_file();
+        let defaults = Settings::default();
+        let (window_width, window_height) = read_window_size(&values);
+        let render_mode = match values.get("render_mode").map(|v| v.trim()) {
+            Some("heat") => RenderMode::Heat,
+            Some("pressure") => RenderMode::Pressure,
+            Some("normal") => RenderMode::Normal,
+            _ => defaults.render_mode,
+        };
+        Settings {
+            tool_radius: Some(read_value(&values, "tool_radius", defaults.tool_radius))
+                .filter(|radius| radius.is_finite() && *radius >= 1.0)
+                .unwrap_or(defaults.tool_radius),
+            tool: values
+                .get("tool")
+                .and_then(|name| parse_tool(name.trim(), manager))
+                .unwrap_or(defaults.tool),
+            group_idx: Some(read_value(&values, "group", defaults.group_idx))
+                .filter(|idx| *idx < manager.groups.borrow().len())
+                .unwrap_or(defaults.group_idx),
+            render_mode,
+            window_width,
+            window_height,
+            heat: read_tool_settings(&values, "heat", defaults.heat),
+            cool: read_tool_settings(&values, "cool", defaults.cool),
+            pressure: read_tool_settings(&values, "pressure", defaults.pressure),
+            autosave_interval: read_value(&values, "autosave_interval", defaults.autosave_interval),
+            history_budget: read_value(&values, "history_budget", defaults.history_budget),
+            keybindings: KeyBindings::read(&values),
+        }
+    }
+
+    // window_conf runs before anything else, so it only reads the size
+    pub fn load_window_size() -> (i32, i32) {
+        read_window_size(&read_settings_file())
+    }
+
+    pub fn save(&self) -> Result<(), DataError> {
+        write_file(&get_settings_path()?, self.to_string())
+    }
+
+    // the render mode, window size, history budget and keybindings aren't part of the properties and are left as they are in base
+    pub fn from_properties(props: &GameProperties, base: Settings) -> Settings {
+        Settings {
+            tool_radius: props.tool_radius,
+            tool: props.tool_type,
+            group_idx: props.selected_group_idx,
+            heat: props.heat_settings,
+            cool: props.cool_settings,
+            pressure: props.pressure_settings,
+            autosave_interval: props.autosave_interval,
+            ..base
+        }
+    }
+}
+
+impl Display for Settings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "tool_radius={}", self.tool_radius)?;
+        writeln!(f, "tool={}", tool_name(self.tool))?;
+        writeln!(f, "group={}", self.group_idx)?;
+        writeln!(f, "render_mode={}", self.render_mode)?;
+        writeln!(f, "window.width={}", self.window_width)?;
+        writeln!(f, "window.height={}", self.window_height)?;
+        write_tool_settings(f, "heat", &self.heat)?;
+        write_tool_settings(f, "cool", &self.cool)?;
+        write_tool_settings(f, "pressure", &self.pressure)?;
+        writeln!(f, "autosave_interval={}", self.autosave_interval)?;
+        writeln!(f, "history_budget={}", self.history_budget)?;
+        write!(f, "{}", self.keybindings)
+    }
+}
+
+fn read_settings_file() -> HashMap<String, String> {
+    let contents = get_settings_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    parse_values(&contents)
+}
+
+// sizes too small to use fall back to the default
+fn read_window_size(values: &HashMap<String, String>) -> (i32, i32) {
+    let width = read_value(values, "window.width", DEFAULT_WINDOW_WIDTH);
+    let height = read_value(values, "window.height", DEFAULT_WINDOW_HEIGHT);
+    if width < 320 || height < 240 {
+        (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT)
+    } else {
+        (width, height)
+    }
+}
+
+// element:<name>, property:<name>, select or picker
+fn tool_name(tool: Tool) -> String {
+    match tool {
+        Tool::ElementTool(variant) => format!("element:{}", variant.get_name()),
+        Tool::PropertyTool(property) => format!("property:{}", property),
+        Tool::SelectTool => "select".to_string(),
+        Tool::PickerTool => "picker".to_string(),
+    }
+}
+
+fn parse_tool(name: &str, manager: &ElementManager) -> Option<Tool> {
+    match name.split_once(':') {
+        Some(("element", element)) => manager
+            .groups
+            .borrow()
+            .iter()
+            .flat_map(|group| group.get_elements())
+            .find(|variant| variant.get_name() == element)
+            .map(Tool::ElementTool),
+        Some(("property", property)) => TOOLS
+            .iter()
+            .find(|tool| tool.to_string() == property)
+            .map(|tool| Tool::PropertyTool(*tool)),
+        _ => match name {
+            "select" => Some(Tool::SelectTool),
+            "picker" => Some(Tool::PickerTool),
+            _ => None,
+        },
+    }
+}
+
+// values are kept as written, leading and trailing spaces can be part of a title or description
+pub fn parse_values(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .collect()
+}
+
+pub fn read_value<T: std::str::FromStr>(
+    values: &HashMap<String, String>,
+    key: &str,
+    default: T,
+) -> T {
+    // a hand edited "radius = 5" still reads as a number
+    values
+        .get(key)
+        .and_then(|value| value.parse().or_else(|_| value.trim().parse()).ok())
+        .unwrap_or(default)
+}
+
+fn read_tool_settings(
+    values: &HashMap<String, String>,
+    prefix: &str,
+    default: ToolSettings,
+) -> ToolSettings {
+    let falloff = match values.get(&format!("{}.falloff", prefix)).map(|v| v.trim()) {
+        Some("gaussian") => Falloff::Gaussian,
+        Some("linear") => Falloff::Linear,
+        Some("flat") => Falloff::Flat,
+        _ => default.falloff,
+    };
+    // a zero spread divides by zero in the falloff, so it falls back like a bad value
+    let read = |key: &str, default: f32, valid: fn(f32) -> bool| {
+        Some(read_value(values, &format!("{}.{}", prefix, key), default))
+            .filter(|v| v.is_finite() && valid(*v))
+            .unwrap_or(default)
+    };
+    ToolSettings {
+        strength: read("strength", default.strength, |v| v >= 0.0),
+        falloff,
+        spread: read("spread", default.spread, |v| v > 0.0),
+        clip_radius: read("clip_radius", default.clip_radius, |v| v >= 0.0),
+    }
+}
+
+fn write_tool_settings(
+    f: &mut std::fmt::Formatter<'_>,
+    prefix: &str,
+    settings: &ToolSettings,
+) -> std::fmt::Result {
+    writeln!(f, "{}.strength={}", prefix, settings.strength)?;
+    writeln!(f, "{}.falloff={}", prefix, settings.falloff)?;
+    writeln!(f, "{}.spread={}", prefix, settings.spread)?;
+    writeln!(f, "{}.clip_radius={}", prefix, settings.clip_radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_keep_spaces_but_numbers_still_read() {
+        let values = parse_values("title=  spaced \n radius = 5\nnot a setting\n");
+        assert_eq!(values["title"], "  spaced ");
+        assert_eq!(read_value(&values, "radius", 0), 5);
+        assert_eq!(read_value(&values, "missing", 7), 7);
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn tool_settings_round_trip() {
+        let settings = Settings {
+            heat: ToolSettings {
+                strength: 12.5,
+                falloff: Falloff::Linear,
+                spread: 3.0,
+                clip_radius: 4.0,
+            },
+            ..Settings::default()
+        };
+        let values = parse_values(&settings.to_string());
+        let heat = read_tool_settings(&values, "heat", ToolSettings::HEAT);
+        assert_eq!(heat, settings.heat);
+        assert_eq!(
+            read_tool_settings(&values, "pressure", ToolSettings::HEAT),
+            ToolSettings::PRESSURE
+        );
+    }
+
+    #[test]
+    fn bad_tool_settings_fall_back() {
+        let values = parse_values(
+            "heat.strength=NaN\nheat.falloff=bumpy\nheat.spread=0\nheat.clip_radius=-1\n",
+        );
+        assert_eq!(
+            read_tool_settings(&values, "heat", ToolSettings::HEAT),
+            ToolSettings::HEAT
+        );
+    }
+
+    #[test]
+    fn tiny_window_sizes_fall_back() {
+        let values = parse_values("window.width=100\nwindow.height=800\n");
+        assert_eq!(
+            read_window_size(&values),
+            (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT)
+        );
+        let values = parse_values("window.width=1024\nwindow.height=768\n");
+        assert_eq!(read_window_size(&values), (1024, 768));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+use silica_engine::{variant::Variant, world::World};
+
+use crate::brush::{Brush, Spray};
+use crate::history::History;
+use crate::manager::{GameProperties, Shape, Tool, WorldInfo};
+use crate::utils::{
+    paint_cell, place_particle, screen_to_world, stroke_line, stroke_spacing, use_tool,
+    world_to_screen,
+};
+
+// polyline the brush follows for the outline shapes, in world coords
+fn shape_path(shape: Shape, start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
+    let (x0, y0) = start;
+    let (x1, y1) = end;
+    match shape {
+        Shape::Line => vec![start, end],
+        Shape::Rect => vec![(x0, y0), (x1, y0), (x1, y1), (x0, y1), (x0, y0)],
+        Shape::Ellipse => {
+            // ellipse inscribed in the box between the two corners
+            let cx = (x0 + x1) as f32 / 2.0;
+            let cy = (y0 + y1) as f32 / 2.0;
+            let rx = (x1 - x0).abs() as f32 / 2.0;
+            let ry = (y1 - y0).abs() as f32 / 2.0;
+            let segments = ((rx + ry) * 2.0).clamp(16.0, 256.0) as usize;
+            (0..=segments)
+                .map(|i| {
+                    let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                    (
+                        (cx + rx * angle.cos()).round() as i32,
+                        (cy + ry * angle.sin()).round() as i32,
+                    )
+                })
+                .collect()
+        }
+        Shape::Freehand | Shape::FilledRect | Shape::Fill => vec![],
+    }
+}
+
+pub fn apply_shape(
+    props: GameProperties,
+    world: &mut World,
+    history: &mut History,
+    spray: &mut Spray,
+    brush: &Brush,
+    start: (i32, i32),
+    end: (i32, i32),
+) {
+    if props.shape == Shape::FilledRect {
+        let (min_x, max_x) = (start.0.min(end.0), start.0.max(end.0));
+        let (min_y, max_y) = (start.1.min(end.1), start.1.max(end.1));
+        match props.tool_type {
+            Tool::ElementTool(variant) => {
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        paint_cell(
+                            world,
+                            history,
+                            spray,
+                            x,
+                            y,
+                            variant,
+                            brush.density,
+                            props.paint_filter,
+                            props.spawn_temperature(),
+                        );
+                    }
+                }
+            }
+            Tool::PropertyTool(_) => {
+                // property tools have no single-cell form, so cover the box with brush stamps
+                let spacing = stroke_spacing(props.tool_radius) as usize;
+                for x in (min_x..=max_x).step_by(spacing) {
+                    for y in (min_y..=max_y).step_by(spacing) {
+                        use_tool(props, world, history, spray, brush, x, y);
+                    }
+                }
+            }
+            Tool::SelectTool | Tool::PickerTool => (),
+        }
+        return;
+    }
+
+    let path = shape_path(props.shape, start, end);
+    let spacing = stroke_spacing(props.tool_radius);
+    if let Some(&(x, y)) = path.first() {
+        use_tool(props, world, history, spray, brush, x, y);
+    }
+    for segment in path.windows(2) {
+        let (x0, y0) = segment[0];
+        let (x1, y1) = segment[1];
+        if (x0, y0) == (x1, y1) {
+            continue;
+        }
+        stroke_line(x0, y0, x1, y1, spacing, |x, y| {
+            use_tool(props, world, history, spray, brush, x, y)
+        });
+    }
+}
+
+// fills the 4-connected region of cells sharing the variant under (x, y)
+pub fn flood_fill(
+    world: &mut World,
+    history: &mut History,
+    x: i32,
+    y: i32,
+    variant: Variant,
+    temperature: Option<f32>,
+) {
+    let width = world.width as i32;
+    let height = world.height as i32;
+    if x < 0 || y < 0 || x >= width || y >= height {
+        return;
+    }
+    let target = world.get_particle(x, y).variant;
+    if target == variant {
+        return;
+    }
+
+    let mut visited = vec![false; (width * height) as usize];
+    let mut queue = VecDeque::new();
+    queue.push_back((x, y));
+    visited[(y * width + x) as usize] = true;
+
+    while let Some((cx, cy)) = queue.pop_front() {
+        place_particle(world, history, cx, cy, variant, temperature);
+        for (nx, ny) in [(cx + 1, cy), (cx - 1, cy), (cx, cy + 1), (cx, cy - 1)] {
+            if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                continue;
+            }
+            let idx = (ny * width + nx) as usize;
+            if visited[idx] || world.get_particle(nx, ny).variant != target {
+                continue;
+            }
+            visited[idx] = true;
+            queue.push_back((nx, ny));
+        }
+    }
+}
+
+// translucent outline of the shape being dragged out, drawn over the world texture
+pub fn draw_shape_preview(world_info: &WorldInfo) {
+    let props = world_info.properties;
+    let Some(start) = props.shape_start else {
+        return;
+    };
+    let (mouse_x, mouse_y) = mouse_position();
+    let (end_x, end_y) = screen_to_world(mouse_x, mouse_y, world_info);
+    let end = (end_x as i32, end_y as i32);
+    let color = Color::new(1.0, 1.0, 1.0, 0.5);
+
+    if props.shape == Shape::FilledRect {
+        let (x0, y0) = world_to_screen(
+            start.0.min(end.0) as f32,
+            start.1.min(end.1) as f32,
+            world_info,
+        );
+        let (x1, y1) = world_to_screen(
+            (start.0.max(end.0) + 1) as f32,
+            (start.1.max(end.1) + 1) as f32,
+            world_info,
+        );
+        draw_rectangle(x0, y0, x1 - x0, y1 - y0, color);
+        return;
+    }
+
+    // brush diameter in screen pixels so the preview matches what gets painted
+    let scale_x = world_to_screen(1.0, 0.0, world_info).0 - world_to_screen(0.0, 0.0, world_info).0;
+    let thickness = (props.tool_radius * 2.0 * scale_x).max(1.0);
+    let path = shape_path(props.shape, start, end);
+    for segment in path.windows(2) {
+        let (x0, y0) = world_to_screen(segment[0].0 as f32, segment[0].1 as f32, world_info);
+        let (x1, y1) = world_to_screen(segment[1].0 as f32, segment[1].1 as f32, world_info);
+        draw_line(x0, y0, x1, y1, thickness, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_path_is_closed() {
+        let path = shape_path(Shape::Rect, (1, 2), (5, 7));
+        assert_eq!(path, vec![(1, 2), (5, 2), (5, 7), (1, 7), (1, 2)]);
+    }
+
+    #[test]
+    fn ellipse_stays_inside_its_box() {
+        let path = shape_path(Shape::Ellipse, (10, 20), (30, 40));
+        assert_eq!(path.first(), path.last());
+        for extreme in [(30, 30), (20, 40), (10, 30), (20, 20)] {
+            assert!(path.contains(&extreme));
+        }
+        for &(x, y) in path.iter() {
+            assert!((10..=30).contains(&x) && (20..=40).contains(&y));
+        }
+    }
+
+    #[test]
+    fn shapes_without_an_outline_have_no_path() {
+        for shape in [Shape::Freehand, Shape::FilledRect, Shape::Fill] {
+            assert!(shape_path(shape, (0, 0), (4, 4)).is_empty());
+        }
+        assert_eq!(
+            shape_path(Shape::Line, (0, 0), (4, 4)),
+            vec![(0, 0), (4, 4)]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+use macroquad::ui::{hash, widgets};
+use silica_engine::world::World;
+
+use crate::clipboard::Region;
+use crate::data::{export_png, get_stamp_dir, path_str, DataError};
+use crate::manager::GameProperties;
+use crate::thumbnails::ThumbnailCache;
+use crate::utils::{draw_placeholder, pinned_window};
+
+// a stamp is a region saved as a tiny world, <name>.slc next to a <name>.png thumbnail
+pub fn save_stamp(region: &Region, name: &str) -> Result<(), DataError> {
+    let mut stamp_dir = get_stamp_dir()?;
+    stamp_dir.push(name);
+    let path = path_str(&stamp_dir)?;
+    region.to_world().save_to_slc(path);
+    export_png(&region.to_image(), &PathBuf::from(format!("{}.png", path)))
+}
+
+pub fn load_stamp(path: &str) -> Option<Region> {
+    let mut world = World::new(1, 1);
+    world.load_from_slc(path);
+    Region::capture(
+        &world,
+        (0, 0),
+        (world.width as i32 - 1, world.height as i32 - 1),
+    )
+}
+
+pub fn draw_stamp_save_dialog(name: &mut String, props: &mut GameProperties) -> bool {
+    let dialog_width = screen_width() * 0.4;
+    let dialog_height = screen_height() * 0.3;
+    let button_height = 50.0;
+    let mut save = false;
+
+    pinned_window(
+        hash!(),
+        vec2(
+            (screen_width() - dialog_width) / 2.0,
+            (screen_height() - dialog_height) / 2.0,
+        ),
+        vec2(dialog_width, dialog_height),
+        |ui| {
+            ui.input_text(hash!(), "Stamp name", name);
+
+            if widgets::Button::new("Save")
+                .position(vec2(10.0, dialog_height - button_height - 20.0))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+                && !name.is_empty()
+            {
+                save = true;
+                props.requested_stamp_save = false;
+            }
+
+            if widgets::Button::new("Cancel")
+                .position(vec2(
+                    dialog_width / 2.0,
+                    dialog_height - button_height - 20.0,
+                ))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                props.requested_stamp_save = false;
+            }
+        },
+    );
+    save
+}
+
+// grid of stamp thumbnails, returns the .slc of the one that was clicked
+pub fn draw_stamp_browser(
+    thumbnails: &mut ThumbnailCache,
+    props: &mut GameProperties,
+) -> Option<PathBuf> {
+    let dialog_width = screen_width() * 0.6;
+    let dialog_height = screen_height() * 0.6;
+    let dialog_x = (screen_width() - dialog_width) / 2.0;
+    let dialog_y = (screen_height() - dialog_height) / 2.0;
+    let button_height = 50.0;
+    let thumb_size = 96.0;
+    let mut picked = None;
+
+    thumbnails.update();
+    pinned_window(
+        hash!(),
+        vec2(dialog_x, dialog_y),
+        vec2(dialog_width, dialog_height),
+        |ui| {
+            let mut x: f32 = 0.;
+            let mut y: f32 = 0.;
+            for path in thumbnails.paths() {
+                let thumb_x = dialog_x + x;
+                let thumb_y = dialog_y + 30. + y;
+                // stamps without a decoded png still get a cell so they can be placed
+                if let Some(thumb) = thumbnails.get(path) {
+                    // stamps are small, scale them to fit a square cell keeping the aspect ratio
+                    let scale = thumb_size / thumb.width.max(thumb.height);
+                    let img_width = thumb.width * scale;
+                    let img_height = thumb.height * scale;
+                    draw_rectangle_lines(
+                        thumb_x,
+                        thumb_y,
+                        thumb_size,
+                        thumb_size,
+                        1.0,
+                        Color::new(1.0, 1.0, 1.0, 0.3),
+                    );
+                    draw_texture_ex(
+                        &thumb.texture,
+                        thumb_x,
+                        thumb_y,
+                        WHITE,
+                        DrawTextureParams {
+                            dest_size: Some(vec2(img_width, img_height)),
+                            ..Default::default()
+                        },
+                    );
+                } else {
+                    draw_placeholder(
+                        thumb_x,
+                        thumb_y,
+                        thumb_size,
+                        thumb_size,
+                        thumbnails.failed(path),
+                    );
+                }
+
+                let (mouse_x, mouse_y) = mouse_position();
+                if is_mouse_button_pressed(MouseButton::Left)
+                    && mouse_x > thumb_x
+                    && mouse_x < thumb_x + thumb_size
+                    && mouse_y > thumb_y
+                    && mouse_y < thumb_y + thumb_size
+                {
+                    picked = Some(path.with_extension("slc"));
+                    props.requested_stamp_load = false;
+                }
+
+                if x + thumb_size * 2.0 > dialog_width {
+                    y += thumb_size;
+                    x = 0.;
+                } else {
+                    x += thumb_size;
+                }
+            }
+
+            if widgets::Button::new("Cancel")
+                .position(vec2(
+                    dialog_width / 2.0,
+                    dialog_height - button_height - 20.0,
+                ))
+                .size(vec2(dialog_width / 2.0 - 20.0, button_height))
+                .ui(ui)
+            {
+                props.requested_stamp_load = false;
+            }
+        },
+    );
+    picked
+}
@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::SystemTime;
+
+use macroquad::prelude::*;
+
+// how often the directory is listed again to pick up new, changed or removed pngs
+const RESCAN_INTERVAL: f64 = 1.0;
+
+pub struct Thumbnail {
+    pub texture: Texture2D,
+    // size of the png on disk, the texture itself is downscaled
+    pub width: f32,
+    pub height: f32,
+    mtime: SystemTime,
+}
+
+// finished decode sent back by the worker, None when the png couldn't be read
+type Decoded = (PathBuf, SystemTime, Option<(Image, u16, u16)>);
+
+// keeps one small texture per png in a directory
+// pngs are decoded and downscaled once on a background thread and again only when their mtime changes
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    #[cfg(target_arch = "wasm32")]
+    max_size: u16,
+    listing: Vec<(PathBuf, SystemTime)>,
+    last_scan: Option<f64>,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    // pngs that couldn't be decoded, with their mtime, so they're only tried again once they change
+    failed: HashMap<PathBuf, SystemTime>,
+    // pngs sent to the worker and not back yet, with the mtime they were sent for
+    pending: HashSet<(PathBuf, SystemTime)>,
+    requests: Sender<(PathBuf, SystemTime)>,
+    results: Receiver<Decoded>,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: PathBuf, max_size: u16) -> ThumbnailCache {
+        let (requests, jobs) = channel::<(PathBuf, SystemTime)>();
+        let (done, results) = channel();
+        // the worker stops once the cache and with it the sender is dropped
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(move || {
+            for (path, mtime) in jobs {
+                let decoded = decode(&path, max_size);
+                if done.send((path, mtime, decoded)).is_err() {
+                    break;
+                }
+            }
+        });
+        // no threads on the web, jobs are decoded on the main thread in update
+        #[cfg(target_arch = "wasm32")]
+        let _ = (jobs, done);
+
+        ThumbnailCache {
+            dir,
+            #[cfg(target_arch = "wasm32")]
+            max_size,
+            listing: vec![],
+            last_scan: None,
+            thumbnails: HashMap::new(),
+            failed: HashMap::new(),
+            pending: HashSet::new(),
+            requests,
+            results,
+        }
+    }
+
+    // call once per frame while the thumbnails are on screen
+    pub fn update(&mut self) {
+        let now = get_time();
+        if self
+            .last_scan
+            .is_none_or(|last| now - last > RESCAN_INTERVAL)
+        {
+            self.last_scan = Some(now);
+            self.scan();
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        for (path, mtime) in self.pending.clone() {
+            let decoded = decode(&path, self.max_size);
+            self.finish((path, mtime, decoded));
+        }
+
+        while let Ok(decoded) = self.results.try_recv() {
+            self.finish(decoded);
+        }
+    }
+
+    // lists the pngs again on the next update, e.g. after something was saved
+    pub fn refresh(&mut self) {
+        self.last_scan = None;
+    }
+
+    // pngs in the directory, sorted by file name
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.listing.iter().map(|(path, _)| path)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Thumbnail> {
+        self.thumbnails.get(path)
+    }
+
+    // true when the png couldn't be decoded, false while it is still waiting for the worker
+    pub fn failed(&self, path: &Path) -> bool {
+        self.failed.contains_key(path)
+    }
+
+    fn scan(&mut self) {
+        let mut listing: Vec<(PathBuf, SystemTime)> = std::fs::read_dir(&self.dir)
+            .map(|dir| {
+                dir.flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("png"))
+                    .filter_map(|path| {
+                        let mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                        Some((path, mtime))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        listing.sort();
+
+        // forget thumbnails of deleted pngs
+        let present: HashSet<&PathBuf> = listing.iter().map(|(path, _)| path).collect();
+        self.thumbnails.retain(|path, _| present.contains(path));
+        self.failed.retain(|path, _| present.contains(path));
+
+        for (path, mtime) in listing.iter() {
+            let fresh = self
+                .thumbnails
+                .get(path)
+                .is_some_and(|thumb| thumb.mtime == *mtime)
+                || self.failed.get(path) == Some(mtime);
+            let job = (path.clone(), *mtime);
+            if !fresh && !self.pending.contains(&job) {
+                self.pending.insert(job.clone());
+                let _ = self.requests.send(job);
+            }
+        }
+        self.listing = listing;
+    }
+
+    fn finish(&mut self, (path, mtime, decoded): Decoded) {
+        self.pending.remove(&(path.clone(), mtime));
+        let Some((image, width, height)) = decoded else {
+            self.thumbnails.remove(&path);
+            self.failed.insert(path, mtime);
+            return;
+        };
+        self.failed.remove(&path);
+        let texture = Texture2D::from_image(&image);
+        // pngs small enough to be kept at full size are shown enlarged, keep their pixels sharp
+        if image.width == width && image.height == height {
+            texture.set_filter(FilterMode::Nearest);
+        } else {
+            texture.set_filter(FilterMode::Linear);
+        }
+        self.thumbnails.insert(
+            path,
+            Thumbnail {
+                texture,
+                width: width as f32,
+                height: height as f32,
+                mtime,
+            },
+        );
+    }
+}
+
+// reads a png and shrinks it so its longest side is at most max_size
+fn decode(path: &Path, max_size: u16) -> Option<(Image, u16, u16)> {
+    let bytes = std::fs::read(path).ok()?;
+    let image = Image::from_file_with_format(&bytes, None).ok()?;
+    let (width, height) = (image.width, image.height);
+    let scale = (max_size as f32 / width.max(height) as f32).min(1.0);
+    let thumb_width = ((width as f32 * scale) as u16).max(1);
+    let thumb_height = ((height as f32 * scale) as u16).max(1);
+    let mut thumb = Image::gen_image_color(thumb_width, thumb_height, BLANK);
+    for y in 0..thumb_height {
+        for x in 0..thumb_width {
+            let src_x = (x as f32 / scale) as u32;
+            let src_y = (y as f32 / scale) as u32;
+            thumb.set_pixel(
+                x as u32,
+                y as u32,
+                image.get_pixel(src_x.min(width as u32 - 1), src_y.min(height as u32 - 1)),
+            );
+        }
+    }
+    Some((thumb, width, height))
+}
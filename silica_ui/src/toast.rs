@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+use macroquad::prelude::*;
+
+use crate::UI_OFFSET_Y;
+
+// how long a message stays on screen, in seconds
+const TOAST_DURATION: f64 = 5.0;
+const TOAST_HEIGHT: f32 = 28.0;
+
+// short messages shown over the world for a few seconds, used for errors that
+// used to panic or go unnoticed
+#[derive(Default)]
+pub struct Toasts {
+    messages: Vec<(String, f64)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Display) {
+        self.messages.push((message.to_string(), get_time()));
+    }
+
+    // keeps the value of an Ok, shows the error of an Err
+    pub fn report<T, E: Display>(&mut self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.push(err);
+                None
+            }
+        }
+    }
+
+    pub fn draw(&mut self) {
+        let now = get_time();
+        self.messages
+            .retain(|(_, shown_at)| now - shown_at < TOAST_DURATION);
+
+        // stacked above the bottom panel
+        let mut y = screen_height() - UI_OFFSET_Y - 30.0 - TOAST_HEIGHT - 10.0;
+        // newest at the bottom
+        for (message, shown_at) in self.messages.iter().rev() {
+            // fade out over the last second
+            let alpha = (TOAST_DURATION - (now - shown_at)).min(1.0) as f32;
+            let size = measure_text(message, None, 20, 1.0);
+            let width = size.width + 20.0;
+            let x = (screen_width() - width) / 2.0;
+            draw_rectangle(
+                x,
+                y,
+                width,
+                TOAST_HEIGHT,
+                Color::new(0.5, 0.05, 0.05, 0.85 * alpha),
+            );
+            draw_text(
+                message,
+                x + 10.0,
+                y + TOAST_HEIGHT / 2.0 + size.offset_y / 2.0,
+                20.0,
+                Color::new(1.0, 1.0, 1.0, alpha),
+            );
+            y -= TOAST_HEIGHT + 4.0;
+        }
+    }
+}
@@ -2,13 +2,16 @@ use ::rand::Rng;
 use macroquad::miniquad::window::cancel_quit;
 use macroquad::prelude::*;
 use macroquad::ui::widgets;
-use macroquad::ui::{hash, root_ui};
+use macroquad::ui::{hash, root_ui, Id, Ui};
 
 use silica_engine::{group::ElementManager, variant::Variant, world::World};
 
+use crate::autosave::end_session;
+use crate::brush::{Brush, BrushShape, Spray};
 use crate::data::get_save_dir;
-use crate::manager::{GameProperties, Property, Tool, WorldInfo};
-use crate::{TOOLS, UI_OFFSET_X, UI_OFFSET_Y};
+use crate::history::History;
+use crate::manager::{Falloff, GameProperties, PaintFilter, Property, Shape, Tool, WorldInfo};
+use crate::{BRUSHES, SHAPES, TOOLS, UI_OFFSET_X, UI_OFFSET_Y};
 
 pub fn draw_walls(world: &mut World) {
     for x in 0..world.width {
@@ -21,8 +24,83 @@ pub fn draw_walls(world: &mut World) {
     }
 }
 
+// used by the void edge mode, anything but walls on the outermost cells is removed
+pub fn clear_edges(world: &mut World) {
+    let width = world.width as i32;
+    let height = world.height as i32;
+    let edges = (0..width)
+        .flat_map(|x| [(x, 0), (x, height - 1)])
+        .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]));
+    for (x, y) in edges {
+        let variant = world.get_particle(x, y).variant;
+        if variant != Variant::Empty && variant != Variant::Wall {
+            world.set_particle(x, y, Variant::Empty);
+        }
+    }
+}
+
+// the world texture is stretched over everything left of the sidebar and above the panels
+pub fn world_view_size() -> (f32, f32) {
+    (screen_width() - UI_OFFSET_X, screen_height() - UI_OFFSET_Y)
+}
+
+// part of the world view the texture is drawn in, letterboxed to keep the world's aspect ratio
+pub fn world_view_rect(world_width: usize, world_height: usize) -> Rect {
+    let (view_w, view_h) = world_view_size();
+    let scale = (view_w / world_width as f32).min(view_h / world_height as f32);
+    let w = world_width as f32 * scale;
+    let h = world_height as f32 * scale;
+    Rect::new((view_w - w) / 2.0, (view_h - h) / 2.0, w, h)
+}
+
+// both mappings go through the camera, so they follow zoom and pan
+pub fn screen_to_world(x: f32, y: f32, world_info: &WorldInfo) -> (f32, f32) {
+    let view = world_view_rect(world_info.world_width, world_info.world_height);
+    let rect = world_info
+        .camera
+        .visible_rect(world_info.world_width, world_info.world_height);
+    (
+        rect.x + (x - view.x) / view.w * rect.w,
+        rect.y + (y - view.y) / view.h * rect.h,
+    )
+}
+
+pub fn world_to_screen(x: f32, y: f32, world_info: &WorldInfo) -> (f32, f32) {
+    let view = world_view_rect(world_info.world_width, world_info.world_height);
+    let rect = world_info
+        .camera
+        .visible_rect(world_info.world_width, world_info.world_height);
+    (
+        view.x + (x - rect.x) / rect.w * view.w,
+        view.y + (y - rect.y) / rect.h * view.h,
+    )
+}
+
+// panels are pinned to the screen edges, unlike root_ui().window they follow window resizes
+pub fn pinned_window(id: Id, position: Vec2, size: Vec2, f: impl FnOnce(&mut Ui)) -> bool {
+    widgets::Window::new(id, position, size)
+        .titlebar(false)
+        .movable(false)
+        .ui(&mut root_ui(), f)
+}
+
+// stands in for a thumbnail that is still decoding or couldn't be decoded
+pub fn draw_placeholder(x: f32, y: f32, width: f32, height: f32, failed: bool) {
+    let faded = Color::new(1.0, 1.0, 1.0, 0.3);
+    draw_rectangle_lines(x, y, width, height, 1.0, faded);
+    let text = if failed { "no preview" } else { "..." };
+    let size = measure_text(text, None, 16, 1.0);
+    draw_text(
+        text,
+        x + (width - size.width) / 2.0,
+        y + (height + size.height) / 2.0,
+        16.0,
+        faded,
+    );
+}
+
 pub fn draw_group_sidebar(manager: &ElementManager, world_info: &mut WorldInfo) {
-    root_ui().window(
+    pinned_window(
         hash!(),
         vec2(screen_width() - UI_OFFSET_X, 30.0),
         vec2(UI_OFFSET_X, screen_height() - 30.0),
@@ -40,30 +118,247 @@ pub fn draw_group_sidebar(manager: &ElementManager, world_info: &mut WorldInfo)
     );
 }
 
-// horizontal left to right assortment of elements
-// shows up on bottom of screen
-// truncated names to 4 chars
-pub fn draw_tool_outline(world_info: &mut WorldInfo) {
-    // Get mouse position
+// text typed into the temperature fields, kept between frames so half typed numbers aren't lost
+pub struct TemperatureInputs {
+    pub set_temperature: String,
+    pub paint_temperature: String,
+    // one of the boxes was clicked last, hotkeys are off so typed digits don't switch views
+    pub editing: bool,
+}
+
+impl TemperatureInputs {
+    pub fn new(props: &GameProperties) -> TemperatureInputs {
+        TemperatureInputs {
+            set_temperature: props.set_temperature.to_string(),
+            paint_temperature: props.paint_temperature.to_string(),
+            editing: false,
+        }
+    }
+}
+
+// the temperature box sits in its own window next to the tool options, the ui doesn't expose
+// which input has focus, so a click inside this rect is taken as focusing it
+const TEMPERATURE_INPUT_RECT: Rect = Rect {
+    x: 300.0,
+    y: 60.0,
+    w: 220.0,
+    h: 40.0,
+};
+
+// options for the current tool, drawn under the top panel while OPTS is on
+pub fn draw_tool_options(
+    manager: &ElementManager,
+    world_info: &mut WorldInfo,
+    inputs: &mut TemperatureInputs,
+) {
+    let variants: Vec<Variant> = manager
+        .groups
+        .borrow()
+        .iter()
+        .flat_map(|group| group.get_elements())
+        .collect();
+    let names: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+    let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+
+    if !world_info.properties.show_tool_options {
+        inputs.editing = false;
+        return;
+    }
+    pinned_window(hash!(), vec2(0.0, 60.0), vec2(300.0, 300.0), |ui| {
+        if let Tool::ElementTool(_) = world_info.properties.tool_type {
+            ui.slider(
+                hash!(),
+                "Density %",
+                1.0..100.0,
+                &mut world_info.properties.spray_density,
+            );
+            filter_options(
+                ui,
+                "Paint",
+                &mut world_info.properties.paint_filter,
+                &["All", "Only empty", "Replace only", "Protect walls"],
+                &variants,
+                &names,
+            );
+            ui.checkbox(
+                hash!(),
+                "Paint at temperature",
+                &mut world_info.properties.paint_at_temperature,
+            );
+        }
+        if let Tool::PropertyTool(property) = world_info.properties.tool_type {
+            let max_strength = if property == Property::Pressure {
+                500.0
+            } else {
+                200.0
+            };
+            if let Some(settings) = world_info.properties.tool_settings_mut(property) {
+                ui.slider(
+                    hash!(),
+                    "Strength",
+                    0.0..max_strength,
+                    &mut settings.strength,
+                );
+                let falloffs = [Falloff::Gaussian, Falloff::Linear, Falloff::Flat];
+                let mut falloff_idx = falloffs
+                    .iter()
+                    .position(|f| *f == settings.falloff)
+                    .unwrap_or(0);
+                ui.combo_box(
+                    hash!(),
+                    "Falloff",
+                    &["gaussian", "linear", "flat"],
+                    &mut falloff_idx,
+                );
+                settings.falloff = falloffs[falloff_idx];
+                if settings.falloff != Falloff::Flat {
+                    ui.slider(hash!(), "Spread", 0.5..50.0, &mut settings.spread);
+                }
+                ui.slider(
+                    hash!(),
+                    "Clip radius",
+                    0.0..100.0,
+                    &mut settings.clip_radius,
+                );
+            }
+        }
+        filter_options(
+            ui,
+            "Erase",
+            &mut world_info.properties.erase_filter,
+            &["All", "Replace only", "Protect walls"],
+            &variants,
+            &names,
+        );
+    });
+
+    let painting_at_temperature = matches!(world_info.properties.tool_type, Tool::ElementTool(_))
+        && world_info.properties.paint_at_temperature;
+    let setting_temperature =
+        world_info.properties.tool_type == Tool::PropertyTool(Property::SetTemp);
+    if !painting_at_temperature && !setting_temperature {
+        inputs.editing = false;
+        return;
+    }
+    if is_mouse_button_pressed(MouseButton::Left) {
+        inputs.editing = TEMPERATURE_INPUT_RECT.contains(mouse_position().into());
+    }
+    pinned_window(
+        hash!(),
+        TEMPERATURE_INPUT_RECT.point(),
+        TEMPERATURE_INPUT_RECT.size(),
+        |ui| {
+            if painting_at_temperature {
+                ui.input_text(hash!(), "Spawn C", &mut inputs.paint_temperature);
+                if let Ok(temperature) = inputs.paint_temperature.trim().parse() {
+                    world_info.properties.paint_temperature = temperature;
+                }
+            } else {
+                ui.input_text(hash!(), "Set to C", &mut inputs.set_temperature);
+                if let Ok(temperature) = inputs.set_temperature.trim().parse() {
+                    world_info.properties.set_temperature = temperature;
+                }
+            }
+        },
+    );
+}
+
+// switches to the element under the cursor and opens the group that holds it
+pub fn pick_element(manager: &ElementManager, props: &mut GameProperties, variant: Variant) {
+    let groups = manager.groups.borrow();
+    let group_idx = groups
+        .iter()
+        .position(|group| group.get_elements().into_iter().any(|e| e == variant));
+    if let Some(group_idx) = group_idx {
+        props.tool_type = Tool::ElementTool(variant);
+        props.selected_group_idx = group_idx;
+    }
+}
+
+pub fn draw_tool_outline(world_info: &mut WorldInfo, brush: &Brush) {
+    // outline the cells the brush covers around the cell under the mouse
+    let (mouse_x, mouse_y) = mouse_position();
+    let (cursor_x, cursor_y) = screen_to_world(mouse_x, mouse_y, world_info);
+    let (cursor_x, cursor_y) = (cursor_x.floor(), cursor_y.floor());
+
+    for &((x0, y0), (x1, y1)) in brush.outline() {
+        let (sx0, sy0) = world_to_screen(cursor_x + x0 as f32, cursor_y + y0 as f32, world_info);
+        let (sx1, sy1) = world_to_screen(cursor_x + x1 as f32, cursor_y + y1 as f32, world_info);
+        draw_line(sx0, sy0, sx1, sy1, 2.0, Color::new(1.0, 1.0, 1.0, 1.0));
+    }
+}
+
+// how much bigger the magnifier shows the world than the main view does
+const MAGNIFIER_ZOOM: f32 = 6.0;
+const MAGNIFIER_SIZE: f32 = 240.0;
+
+// square inset next to the cursor showing the world around it enlarged, the main view is left alone
+pub fn draw_magnifier(texture: &Texture2D, world_info: &WorldInfo) {
+    let (view_w, view_h) = world_view_size();
+    let view = world_view_rect(world_info.world_width, world_info.world_height);
     let (mouse_x, mouse_y) = mouse_position();
-    let mouse_x = mouse_x as f32;
-    let mouse_y = mouse_y as f32;
+    let (cursor_x, cursor_y) = screen_to_world(mouse_x, mouse_y, world_info);
+    let rect = world_info
+        .camera
+        .visible_rect(world_info.world_width, world_info.world_height);
+
+    // cells that fit in the inset at the magnified scale
+    let cells_w = MAGNIFIER_SIZE / MAGNIFIER_ZOOM * rect.w / view.w;
+    let cells_h = MAGNIFIER_SIZE / MAGNIFIER_ZOOM * rect.h / view.h;
+    let source = Rect::new(
+        cursor_x.floor() + 0.5 - cells_w / 2.0,
+        cursor_y.floor() + 0.5 - cells_h / 2.0,
+        cells_w,
+        cells_h,
+    );
 
-    let radius = world_info.properties.tool_radius;
+    // sits below right of the cursor, flipped to the other side near the edges
+    let offset = 30.0;
+    let x = if mouse_x + offset + MAGNIFIER_SIZE > view_w {
+        mouse_x - offset - MAGNIFIER_SIZE
+    } else {
+        mouse_x + offset
+    };
+    let y = if mouse_y + offset + MAGNIFIER_SIZE > view_h {
+        mouse_y - offset - MAGNIFIER_SIZE
+    } else {
+        mouse_y + offset
+    };
 
-    draw_circle_lines(
-        mouse_x,
-        mouse_y,
-        radius,
-        2.0,
-        Color::new(1.0, 1.0, 1.0, 1.0),
+    draw_rectangle(x, y, MAGNIFIER_SIZE, MAGNIFIER_SIZE, BLACK);
+    draw_texture_ex(
+        texture,
+        x,
+        y,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(vec2(MAGNIFIER_SIZE, MAGNIFIER_SIZE)),
+            source: Some(source),
+            ..Default::default()
+        },
     );
+
+    // outline the cell under the cursor
+    let cell_w = MAGNIFIER_SIZE / cells_w;
+    let cell_h = MAGNIFIER_SIZE / cells_h;
+    draw_rectangle_lines(
+        x + (MAGNIFIER_SIZE - cell_w) / 2.0,
+        y + (MAGNIFIER_SIZE - cell_h) / 2.0,
+        cell_w,
+        cell_h,
+        1.0,
+        Color::new(1.0, 1.0, 1.0, 0.8),
+    );
+    draw_rectangle_lines(x, y, MAGNIFIER_SIZE, MAGNIFIER_SIZE, 2.0, WHITE);
 }
 
+// horizontal left to right assortment of elements
+// shows up on bottom of screen
+// truncated names to 4 chars
 pub fn draw_element_list(manager: &ElementManager, world_info: &mut WorldInfo) {
     let button_size = 50.;
 
-    root_ui().window(
+    pinned_window(
         hash!(),
         vec2(0.0, screen_height() - button_size),
         vec2(screen_width(), 30.0 + button_size),
@@ -104,14 +399,14 @@ pub fn draw_element_list(manager: &ElementManager, world_info: &mut WorldInfo) {
 }
 
 // for tools
-pub fn draw_bottom_panel(world_info: &mut WorldInfo, _gameprops: &mut GameProperties) {
+pub fn draw_bottom_panel(world_info: &mut WorldInfo, gameprops: &mut GameProperties) {
     // draw right above the element panel
 
     let panel_height = 30.0; // Adjust the height of the top panel as needed
     let button_width: f32 = 50.0;
     // draw some buttons for the three tools, selectable too
 
-    root_ui().window(
+    pinned_window(
         hash!(),
         vec2(0.0, screen_height() - UI_OFFSET_Y - 30.),
         vec2(screen_width(), panel_height + 30.),
@@ -137,6 +432,9 @@ pub fn draw_bottom_panel(world_info: &mut WorldInfo, _gameprops: &mut GameProper
                             world_info.properties.tool_type =
                                 Tool::PropertyTool(Property::Pressure);
                         }
+                        Property::SetTemp => {
+                            world_info.properties.tool_type = Tool::PropertyTool(Property::SetTemp);
+                        }
 
                         Property::DelWall => {
                             world_info.properties.tool_type = Tool::PropertyTool(Property::DelWall);
@@ -145,6 +443,92 @@ pub fn draw_bottom_panel(world_info: &mut WorldInfo, _gameprops: &mut GameProper
                 }
                 x += button_width;
             }
+
+            // shape buttons sit after the property tools, clicking the active one goes back to freehand
+            x += button_width / 2.0;
+            for shape in SHAPES {
+                let button = widgets::Button::new(shape.to_string())
+                    .position(vec2(x, y))
+                    .selected(world_info.properties.shape == shape)
+                    .size(vec2(button_width, panel_height))
+                    .ui(ui);
+                if button {
+                    world_info.properties.shape = if world_info.properties.shape == shape {
+                        Shape::Freehand
+                    } else {
+                        shape
+                    };
+                    world_info.properties.shape_start = None;
+                }
+                x += button_width;
+            }
+
+            x += button_width / 2.0;
+            if widgets::Button::new("SEL")
+                .position(vec2(x, y))
+                .selected(world_info.properties.tool_type == Tool::SelectTool)
+                .size(vec2(button_width, panel_height))
+                .ui(ui)
+            {
+                world_info.properties.tool_type = Tool::SelectTool;
+            }
+            x += button_width;
+            if widgets::Button::new("PICK")
+                .position(vec2(x, y))
+                .selected(world_info.properties.tool_type == Tool::PickerTool)
+                .size(vec2(button_width, panel_height))
+                .ui(ui)
+            {
+                world_info.properties.tool_type = Tool::PickerTool;
+            }
+            x += button_width;
+
+            // clicking CSTM again cycles through the pngs in the brushes dir
+            x += button_width / 2.0;
+            for brush_shape in BRUSHES {
+                let button = widgets::Button::new(brush_shape.to_string())
+                    .position(vec2(x, y))
+                    .selected(world_info.properties.brush_shape == brush_shape)
+                    .size(vec2(button_width, panel_height))
+                    .ui(ui);
+                if button {
+                    if brush_shape == BrushShape::Custom
+                        && world_info.properties.brush_shape == BrushShape::Custom
+                    {
+                        world_info.properties.custom_brush_idx += 1;
+                    }
+                    world_info.properties.brush_shape = brush_shape;
+                }
+                x += button_width;
+            }
+
+            x += button_width / 2.0;
+            if widgets::Button::new("OPTS")
+                .position(vec2(x, y))
+                .selected(world_info.properties.show_tool_options)
+                .size(vec2(button_width, panel_height))
+                .ui(ui)
+            {
+                world_info.properties.show_tool_options = !world_info.properties.show_tool_options;
+            }
+            x += button_width;
+
+            x += button_width / 2.0;
+            if widgets::Button::new("NEW")
+                .position(vec2(x, y))
+                .size(vec2(button_width, panel_height))
+                .ui(ui)
+            {
+                gameprops.requested_new = true;
+            }
+            x += button_width;
+            if widgets::Button::new("KEYS")
+                .position(vec2(x, y))
+                .size(vec2(button_width, panel_height))
+                .ui(ui)
+            {
+                gameprops.requested_keybindings = true;
+            }
         },
     );
 }
@@ -213,117 +597,262 @@ pub fn draw_top_panel(world_info: &mut WorldInfo) {
     );
 }
 
-pub fn paint_radius(world: &mut World, x: i32, y: i32, variant: Variant, radius: i32) {
-    let center_x = x as f32 + 0.1; // Adding 0.5 for a half-pixel offset to center particles
-    let center_y = y as f32 + 0.1;
+#[allow(clippy::too_many_arguments)]
+pub fn paint_radius(
+    world: &mut World,
+    history: &mut History,
+    spray: &mut Spray,
+    x: i32,
+    y: i32,
+    variant: Variant,
+    brush: &Brush,
+    filter: PaintFilter,
+    temperature: Option<f32>,
+) {
+    for &(dx, dy) in brush.offsets.iter() {
+        paint_cell(
+            world,
+            history,
+            spray,
+            x + dx,
+            y + dy,
+            variant,
+            brush.density,
+            filter,
+            temperature,
+        );
+    }
+}
 
-    for dx in -radius..radius {
-        for dy in -radius..radius {
-            let distance_squared = (dx * dx + dy * dy) as f32;
-            let radius_squared = radius as f32 * radius as f32;
+// one cell of a brush or filled shape, skipped when the spray or the paint filter says so
+#[allow(clippy::too_many_arguments)]
+pub fn paint_cell(
+    world: &mut World,
+    history: &mut History,
+    spray: &mut Spray,
+    x: i32,
+    y: i32,
+    variant: Variant,
+    density: f32,
+    filter: PaintFilter,
+    temperature: Option<f32>,
+) {
+    if density < 100.0
+        && !spray.decide(x, y, || {
+            ::rand::thread_rng().gen_range(0.0..100.0) < density
+        })
+    {
+        return;
+    }
+    if !filter_allows(world, x, y, filter) {
+        return;
+    }
+    place_particle(world, history, x, y, variant, temperature);
+}
 
-            if distance_squared < radius_squared {
-                let particle_x = center_x + dx as f32;
-                let particle_y = center_y + dy as f32;
+// sets a particle and, when given, the temperature of that particle
+// cells the engine didn't change, like walls under sand, keep their temperature
+pub fn place_particle(
+    world: &mut World,
+    history: &mut History,
+    x: i32,
+    y: i32,
+    variant: Variant,
+    temperature: Option<f32>,
+) {
+    if x < 0 || y < 0 || x >= world.width as i32 || y >= world.height as i32 {
+        return;
+    }
+    history.set_particle(world, x, y, variant);
+    if let Some(temperature) = temperature {
+        if variant != Variant::Empty && world.get_particle(x, y).variant == variant {
+            history.set_particle_temperature(world, x, y, temperature);
+        }
+    }
+}
 
-                let _probability_to_draw = ::rand::thread_rng().gen_range(0..100);
+// sets both the ambient temperature and the particle in the cell, if there is one
+// ambient temperature only changes through add_heat, so the difference to the target is added
+pub fn set_temperature(world: &mut World, history: &mut History, x: i32, y: i32, temperature: f32) {
+    if x < 0 || y < 0 || x >= world.width as i32 || y >= world.height as i32 {
+        return;
+    }
+    let heat = temperature - world.get_temperature(x, y);
+    history.add_heat(world, x, y, heat);
+    if world.get_particle(x, y).variant != Variant::Empty {
+        history.set_particle_temperature(world, x, y, temperature);
+    }
+}
 
-                world.set_particle(particle_x as i32, particle_y as i32, variant);
-            }
-        }
+// distance between brush stamps along a stroke, small enough that stamps overlap
+pub fn stroke_spacing(radius: f32) -> f32 {
+    (radius / 2.0).max(1.0)
+}
+
+// calls stamp at evenly spaced points after (x0, y0) up to and including (x1, y1)
+// the start point is skipped since it was already stamped on the previous frame
+pub fn stroke_line(
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+    spacing: f32,
+    mut stamp: impl FnMut(i32, i32),
+) {
+    if (x0, y0) == (x1, y1) {
+        stamp(x1, y1);
+        return;
+    }
+    let dx = (x1 - x0) as f32;
+    let dy = (y1 - y0) as f32;
+    let steps = ((dx * dx + dy * dy).sqrt() / spacing).ceil().max(1.0) as i32;
+    for i in 1..=steps {
+        let t = i as f32 / steps as f32;
+        stamp(
+            (x0 as f32 + dx * t).round() as i32,
+            (y0 as f32 + dy * t).round() as i32,
+        );
     }
 }
 
-pub fn use_tool(props: GameProperties, world: &mut World, x: i32, y: i32) {
+pub fn use_tool(
+    props: GameProperties,
+    world: &mut World,
+    history: &mut History,
+    spray: &mut Spray,
+    brush: &Brush,
+    x: i32,
+    y: i32,
+) {
     match props.tool_type {
         Tool::ElementTool(variant) => {
-            let radius: i32 = props.tool_radius as i32;
-            paint_radius(world, x, y, variant, radius);
+            paint_radius(
+                world,
+                history,
+                spray,
+                x,
+                y,
+                variant,
+                brush,
+                props.paint_filter,
+                props.spawn_temperature(),
+            );
         }
         Tool::PropertyTool(property) => match property {
             Property::Temperature => {
                 //world.set_temperature(x, y, 100.0);
-                let radius: i32 = props.tool_radius as i32;
-                for dx in -radius..radius {
-                    for dy in -radius..radius {
-                        let distance_squared = (dx * dx + dy * dy) as f32;
-                        let _radius_squared = radius as f32 * radius as f32;
-
-                        if distance_squared > _radius_squared {
-                            continue;
-                        }
-                        let sigm: f32 = 10.;
-                        let exp = -distance_squared / (2.0 * sigm.powi(2));
-                        // intensity based on radius
-
-                        world.add_heat(x + dx, y + dy, 40. * exp.exp());
+                for &(dx, dy) in brush.offsets.iter() {
+                    if let Some(heat) = props.heat_settings.intensity(dx, dy) {
+                        history.add_heat(world, x + dx, y + dy, heat);
                     }
                 }
             }
             Property::Pressure => {
-                //world.set_pressure(x, y, 100);
-                let radius: i32 = props.tool_radius as i32;
-                for dx in -radius..radius {
-                    for dy in -radius..radius {
-                        let distance_squared = (dx * dx + dy * dy) as f32;
-                        let _radius_squared = radius as f32 * radius as f32;
-                        let sigm: f32 = 0.5;
-                        let exp = -distance_squared / (2.0 * sigm.powi(2));
-
-                        world.set_pressure(x + dx, y + dy, 100. * exp.exp());
+                for &(dx, dy) in brush.offsets.iter() {
+                    if let Some(pressure) = props.pressure_settings.intensity(dx, dy) {
+                        // pressure dissipates on its own, so it is not recorded in history
+                        world.set_pressure(x + dx, y + dy, pressure);
                     }
                 }
             }
 
             Property::COOL => {
-                //world.set_pressure(x, y, 100);
-                let radius: i32 = props.tool_radius as i32;
-                for dx in -radius..radius {
-                    for dy in -radius..radius {
-                        let distance_squared = (dx * dx + dy * dy) as f32;
-                        let _radius_squared = radius as f32 * radius as f32;
-
-                        if distance_squared > _radius_squared {
-                            continue;
-                        }
-                        let sigm: f32 = 10.;
-                        let exp = -distance_squared / (2.0 * sigm.powi(2));
-
-                        world.add_heat(x + dx, y + dy, -40. * exp.exp());
+                for &(dx, dy) in brush.offsets.iter() {
+                    if let Some(heat) = props.cool_settings.intensity(dx, dy) {
+                        history.add_heat(world, x + dx, y + dy, -heat);
                     }
                 }
             }
 
+            Property::SetTemp => {
+                for &(dx, dy) in brush.offsets.iter() {
+                    set_temperature(world, history, x + dx, y + dy, props.set_temperature);
+                }
+            }
+
             Property::DelWall => {
-                let radius: i32 = props.tool_radius as i32;
-                erase_indestructible(world, x, y, radius);
+                erase_indestructible(world, history, x, y, brush);
             }
         },
+        // selecting and picking are handled by the main loop, they never touch the world
+        Tool::SelectTool | Tool::PickerTool => (),
     }
 }
 
-pub fn erase_radius(world: &mut World, x: i32, y: i32, radius: i32) {
-    for dx in -radius..radius {
-        for dy in -radius..radius {
-            if dx * dx + dy * dy < radius * radius {
-                world.set_particle(x + dx, y + dy, Variant::Empty);
-            }
+pub fn erase_radius(
+    world: &mut World,
+    history: &mut History,
+    x: i32,
+    y: i32,
+    brush: &Brush,
+    filter: PaintFilter,
+) {
+    for &(dx, dy) in brush.offsets.iter() {
+        if !filter_allows(world, x + dx, y + dy, filter) {
+            continue;
         }
+        history.set_particle(world, x + dx, y + dy, Variant::Empty);
     }
 }
 
-pub fn erase_indestructible(world: &mut World, x: i32, y: i32, radius: i32) {
-    for dx in -radius..radius {
-        for dy in -radius..radius {
-            if dx * dx + dy * dy < radius * radius {
-                world.erase_indestructible(dx + x, dy + y);
-            }
-        }
+fn filter_allows(world: &World, x: i32, y: i32, filter: PaintFilter) -> bool {
+    if x < 0 || y < 0 || x >= world.width as i32 || y >= world.height as i32 {
+        return false;
+    }
+    filter.allows(world.get_particle(x, y).variant)
+}
+
+// combo boxes for a paint filter, modes is the subset that makes sense for the caller
+// the source list only shows up for ReplaceOnly
+fn filter_options(
+    ui: &mut Ui,
+    label: &str,
+    filter: &mut PaintFilter,
+    modes: &[&str],
+    variants: &[Variant],
+    names: &[&str],
+) {
+    let mut source_idx = match filter {
+        PaintFilter::ReplaceOnly(source) => variants.iter().position(|v| v == source).unwrap_or(0),
+        _ => 0,
+    };
+    let current = match filter {
+        PaintFilter::All => "All",
+        PaintFilter::OnlyEmpty => "Only empty",
+        PaintFilter::ReplaceOnly(_) => "Replace only",
+        PaintFilter::ProtectWalls => "Protect walls",
+    };
+    let mut mode_idx = modes.iter().position(|m| *m == current).unwrap_or(0);
+    ui.combo_box(hash!(label, "mode"), label, modes, &mut mode_idx);
+    if modes[mode_idx] == "Replace only" {
+        ui.combo_box(hash!(label, "source"), "Source", names, &mut source_idx);
+    }
+
+    *filter = match modes[mode_idx] {
+        "Only empty" => PaintFilter::OnlyEmpty,
+        "Replace only" => match variants.get(source_idx) {
+            Some(source) => PaintFilter::ReplaceOnly(*source),
+            None => PaintFilter::All,
+        },
+        "Protect walls" => PaintFilter::ProtectWalls,
+        _ => PaintFilter::All,
+    };
+}
+
+pub fn erase_indestructible(
+    world: &mut World,
+    history: &mut History,
+    x: i32,
+    y: i32,
+    brush: &Brush,
+) {
+    for &(dx, dy) in brush.offsets.iter() {
+        history.erase_indestructible(world, dx + x, dy + y);
     }
 }
 
 pub fn draw_confirm_exit(mut props: GameProperties) {
-    root_ui().window(
+    pinned_window(
         hash!(),
         vec2(screen_width() / 2.0 - 100.0, screen_height() / 2.0 - 50.0),
         vec2(200.0, 100.0),
@@ -338,6 +867,8 @@ pub fn draw_confirm_exit(mut props: GameProperties) {
                 .size(vec2(100.0, 50.0))
                 .ui(ui)
             {
+                // a clean exit, the next start won't offer to restore an autosave
+                end_session();
                 std::process::exit(0);
             }
 